@@ -0,0 +1,58 @@
+//! Passive effects of abilities that do not need a script
+
+use hashbrown::HashMap;
+use serde::{Deserialize, Serialize};
+
+use battle::{
+    moves::Percent,
+    pokedex::{
+        ailment::{Ailment, AilmentLength},
+        types::PokemonType,
+    },
+    pokemon::{
+        stat::{BattleStatType, Stage},
+        AbilityId,
+    },
+};
+
+/// What an ability does. Abilities can also have scripts run on triggers.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Ability {
+    /// Multipliers of the damage of moves the pokemon uses by their type
+    #[serde(default)]
+    pub attack: Vec<(PokemonType, f32)>,
+    /// Multipliers of the damage of moves the pokemon is hit by, by their type
+    #[serde(default)]
+    pub defense: Vec<(PokemonType, f32)>,
+    /// Types of moves that do not affect the pokemon
+    #[serde(default)]
+    pub immune: Vec<PokemonType>,
+    /// Ailments the pokemon cannot be given
+    #[serde(default)]
+    pub prevent_ailments: Vec<Ailment>,
+    /// Stats changed when the pokemon enters the field
+    #[serde(default)]
+    pub switch_in: Vec<(AbilityTarget, BattleStatType, Stage)>,
+    /// Effects on pokemon that hit it with a move that makes contact
+    #[serde(default)]
+    pub contact: Vec<ContactEffect>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AbilityTarget {
+    /// The pokemon with the ability
+    User,
+    /// Every active pokemon of the other teams
+    Opponents,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum ContactEffect {
+    /// Give the attacker an ailment with a chance
+    Ailment(Ailment, AilmentLength, Percent),
+    /// Damage the attacker by a percent of its max HP
+    Damage(Percent),
+}
+
+pub type EngineAbilities = HashMap<AbilityId, Ability>;
@@ -0,0 +1,78 @@
+//! Passive effects that actions are run with
+
+use battle::{
+    data::BattleData,
+    moves::DamageModifiers,
+    pokedex::{ailment::Ailment, item::ItemId, types::PokemonType},
+    pokemon::{AbilityId, BattlePokemon},
+    side::Side,
+};
+
+use crate::{
+    ability::{Ability, EngineAbilities},
+    field::Field,
+    item::{EngineHeldItems, HeldItem},
+};
+
+/// Passive effects of the battle and its pokemon.
+#[derive(Clone, Copy)]
+pub struct Effects<'a> {
+    pub field: &'a Field,
+    pub abilities: &'a EngineAbilities,
+    pub held: &'a EngineHeldItems,
+}
+
+impl<'a> Effects<'a> {
+    pub fn ability(&self, pokemon: &BattlePokemon) -> Option<(&'a AbilityId, &'a Ability)> {
+        pokemon
+            .ability
+            .as_ref()
+            .and_then(|id| self.abilities.get_key_value(id))
+    }
+
+    pub fn held(&self, pokemon: &BattlePokemon) -> Option<(&'a ItemId, &'a HeldItem)> {
+        pokemon
+            .item
+            .as_ref()
+            .and_then(|item| self.held.get_key_value(&item.id))
+    }
+
+    /// Damage modifiers of moves used on or by a pokemon.
+    pub fn modifiers(
+        &self,
+        battle: &BattleData,
+        side: &Side,
+        pokemon: &BattlePokemon,
+    ) -> DamageModifiers {
+        let mut modifiers = self.field.modifiers(battle).side(side);
+        if let Some((_, ability)) = self.ability(pokemon) {
+            modifiers.types.extend(ability.defense.iter().copied());
+            modifiers.immune.extend(ability.immune.iter().copied());
+            modifiers.attack.extend(ability.attack.iter().copied());
+        }
+        if let Some((_, item)) = self.held(pokemon) {
+            modifiers.attack.extend(item.attack.iter().copied());
+            modifiers.stats.extend(item.choice);
+            modifiers.boosts.push(item.damage);
+        }
+        modifiers
+    }
+
+    /// The ability of a pokemon if it is immune to moves of a type.
+    pub fn immune(&self, pokemon: &BattlePokemon, move_type: PokemonType) -> Option<&'a AbilityId> {
+        self.ability(pokemon)
+            .filter(|(_, ability)| ability.immune.contains(&move_type))
+            .map(|(id, _)| id)
+    }
+
+    /// The ability of a pokemon if it prevents an ailment.
+    pub fn prevents_ailment(
+        &self,
+        pokemon: &BattlePokemon,
+        ailment: Ailment,
+    ) -> Option<&'a AbilityId> {
+        self.ability(pokemon)
+            .filter(|(_, ability)| ability.prevent_ailments.contains(&ailment))
+            .map(|(id, _)| id)
+    }
+}
@@ -0,0 +1,264 @@
+//! Field conditions, such as terrains, Trick Room and Gravity
+
+use hashbrown::HashMap;
+use serde::{Deserialize, Serialize};
+
+use battle::{
+    data::{BattleData, FieldEvent, FieldId},
+    moves::{Accuracy, DamageModifiers, Percent},
+    pokedex::types::PokemonType,
+};
+
+/// What a field condition does while it is in effect.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FieldCondition {
+    /// How many turns the condition lasts
+    pub turns: u8,
+    /// Conditions of the same group replace each other, like terrains.
+    #[serde(default)]
+    pub group: Option<FieldId>,
+    /// Starting the condition again ends it, like Trick Room.
+    #[serde(default)]
+    pub toggle: bool,
+    /// Multipliers of the damage of moves by their type
+    #[serde(default)]
+    pub damage: Vec<(PokemonType, f32)>,
+    /// Slower pokemon move first
+    #[serde(default)]
+    pub slower_first: bool,
+    /// Grounded pokemon cannot be given ailments, like with Misty Terrain
+    #[serde(default)]
+    pub prevent_ailments: bool,
+    /// Percent of max HP grounded pokemon heal at the end of each turn, like with Grassy Terrain
+    #[serde(default)]
+    pub heal: Percent,
+    /// Multiplier of the accuracy of moves
+    #[serde(default = "one")]
+    pub accuracy: f32,
+}
+
+pub type EngineFields = HashMap<FieldId, FieldCondition>;
+
+/// Field conditions in effect and the turns they have left.
+#[derive(Debug, Default, Clone)]
+pub struct Field(Vec<(FieldId, FieldCondition, u8)>);
+
+impl Field {
+    pub fn ids(&self) -> impl Iterator<Item = &FieldId> + '_ {
+        self.0.iter().map(|(id, ..)| id)
+    }
+
+    pub fn contains(&self, id: &FieldId) -> bool {
+        self.ids().any(|i| i == id)
+    }
+
+    fn conditions(&self) -> impl Iterator<Item = &FieldCondition> + '_ {
+        self.0.iter().map(|(_, condition, _)| condition)
+    }
+
+    /// Damage modifiers of the battle and the field.
+    pub fn modifiers(&self, battle: &BattleData) -> DamageModifiers {
+        let mut modifiers = DamageModifiers::from(battle);
+        modifiers
+            .types
+            .extend(self.conditions().flat_map(|c| c.damage.iter().copied()));
+        modifiers
+    }
+
+    pub fn slower_first(&self) -> bool {
+        self.conditions().any(|c| c.slower_first)
+    }
+
+    pub fn prevents_ailments(&self) -> bool {
+        self.conditions().any(|c| c.prevent_ailments)
+    }
+
+    /// Percent of max HP healed at the end of a turn.
+    pub fn heal(&self) -> Percent {
+        self.conditions().map(|c| c.heal).max().unwrap_or_default()
+    }
+
+    pub fn accuracy(&self, accuracy: Option<Accuracy>) -> Option<Accuracy> {
+        let multiplier = self.conditions().map(|c| c.accuracy).product::<f32>();
+        accuracy.map(|a| (a as f32 * multiplier).min(100.0) as Accuracy)
+    }
+
+    /// Start a condition, ending the others in its group.
+    pub fn start(&mut self, id: FieldId, condition: &FieldCondition) -> Vec<FieldEvent> {
+        let mut events = Vec::new();
+
+        if let Some(group) = condition.group {
+            self.0
+                .retain(|(other, c, ..)| match c.group == Some(group) {
+                    true => {
+                        events.push(FieldEvent::End(*other));
+                        false
+                    }
+                    false => true,
+                });
+        }
+
+        self.0.push((id, condition.clone(), condition.turns));
+        events.push(FieldEvent::Start(id));
+        events
+    }
+
+    /// End a condition, returning false if it was not in effect.
+    pub fn end(&mut self, id: &FieldId) -> bool {
+        let len = self.0.len();
+        self.0.retain(|(other, ..)| other != id);
+        self.0.len() != len
+    }
+
+    /// Count down a turn, returning the conditions that ended.
+    pub fn tick(&mut self) -> Vec<FieldId> {
+        let mut ended = Vec::new();
+        self.0.retain_mut(|(id, _, turns)| {
+            *turns = turns.saturating_sub(1);
+            match *turns == 0 {
+                true => {
+                    ended.push(*id);
+                    false
+                }
+                false => true,
+            }
+        });
+        ended
+    }
+
+    pub fn clear(&mut self) {
+        self.0.clear()
+    }
+}
+
+const fn one() -> f32 {
+    1.0
+}
+
+#[cfg(test)]
+mod tests {
+
+    use rand::rngs::mock::StepRng;
+
+    use battle::{
+        data::{BattleData, FieldEvent, FieldId},
+        pokedex::types::PokemonType,
+        pokemon::{Indexed, TeamIndex},
+        select::PublicAction,
+    };
+
+    use crate::{scripting::RhaiScriptingEngine, DefaultBattleEngine};
+
+    use super::{Field, FieldCondition};
+
+    fn condition(turns: u8, group: Option<FieldId>, toggle: bool) -> FieldCondition {
+        FieldCondition {
+            turns,
+            group,
+            toggle,
+            damage: vec![(PokemonType::Electric, 1.5)],
+            slower_first: toggle,
+            prevent_ailments: false,
+            heal: 0,
+            accuracy: 1.0,
+        }
+    }
+
+    fn id(id: &str) -> FieldId {
+        id.parse().unwrap()
+    }
+
+    #[test]
+    fn group() {
+        let terrain = Some(id("terrain"));
+        let mut field = Field::default();
+
+        assert_eq!(
+            field.start(id("electric"), &condition(5, terrain, false)),
+            vec![FieldEvent::Start(id("electric"))]
+        );
+        assert_eq!(
+            field.start(id("gravity"), &condition(5, None, false)),
+            vec![FieldEvent::Start(id("gravity"))]
+        );
+        assert_eq!(
+            field.start(id("grassy"), &condition(5, terrain, false)),
+            vec![
+                FieldEvent::End(id("electric")),
+                FieldEvent::Start(id("grassy"))
+            ]
+        );
+
+        assert!(!field.contains(&id("electric")));
+        assert!(field.contains(&id("gravity")));
+        assert!(field.contains(&id("grassy")));
+
+        assert_eq!(
+            field.modifiers(&BattleData::default()).types,
+            vec![(PokemonType::Electric, 1.5), (PokemonType::Electric, 1.5)]
+        );
+
+        assert!(field.end(&id("gravity")));
+        assert!(!field.end(&id("gravity")));
+    }
+
+    #[test]
+    fn tick() {
+        let mut field = Field::default();
+        field.start(id("short"), &condition(1, None, false));
+        field.start(id("long"), &condition(3, None, false));
+
+        assert_eq!(field.tick(), vec![id("short")]);
+        assert!(field.tick().is_empty());
+        assert_eq!(field.tick(), vec![id("long")]);
+        assert_eq!(field.ids().count(), 0);
+    }
+
+    #[test]
+    fn toggle() {
+        let mut engine = DefaultBattleEngine::<u8, (), RhaiScriptingEngine>::new::<StepRng>();
+        engine
+            .fields
+            .insert(id("trickroom"), condition(5, None, true));
+        engine
+            .fields
+            .insert(id("gravity"), condition(5, None, false));
+
+        let mut field = Field::default();
+
+        let start = |id: FieldId| {
+            vec![Indexed(
+                TeamIndex(0, 0),
+                PublicAction::Field(FieldEvent::Start(id)),
+            )]
+        };
+
+        let results = engine.update_field(&mut field, start(id("trickroom")));
+        assert!(matches!(
+            results.as_slice(),
+            [Indexed(_, PublicAction::Field(FieldEvent::Start(..)))]
+        ));
+        assert!(field.slower_first());
+
+        let results = engine.update_field(&mut field, start(id("trickroom")));
+        assert!(matches!(
+            results.as_slice(),
+            [Indexed(_, PublicAction::Field(FieldEvent::End(..)))]
+        ));
+        assert!(!field.slower_first());
+
+        engine.update_field(&mut field, start(id("gravity")));
+        let results = engine.update_field(&mut field, start(id("gravity")));
+        assert!(matches!(
+            results.as_slice(),
+            [Indexed(_, PublicAction::Cancel(..))]
+        ));
+        assert!(field.contains(&id("gravity")));
+
+        // conditions that are not registered are left out
+        assert!(engine
+            .update_field(&mut field, start(id("unknown")))
+            .is_empty());
+    }
+}
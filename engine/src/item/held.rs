@@ -0,0 +1,65 @@
+use hashbrown::HashMap;
+use serde::{Deserialize, Serialize};
+
+use battle::{
+    moves::Percent,
+    pokedex::{
+        item::ItemId,
+        pokemon::{stat::StatType, Health},
+        types::PokemonType,
+    },
+    pokemon::BattlePokemon,
+};
+
+/// What an item does while a pokemon holds it. Held items can also have scripts run on triggers.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HeldItem {
+    /// Percent of max HP healed at the end of each turn
+    #[serde(default)]
+    pub heal: Percent,
+    /// Eaten when the holder's HP falls low enough
+    #[serde(default)]
+    pub berry: Option<Berry>,
+    /// Multipliers of the damage of moves the holder uses by their type
+    #[serde(default)]
+    pub attack: Vec<(PokemonType, f32)>,
+    /// Multiplier of the damage of every move the holder uses
+    #[serde(default = "one")]
+    pub damage: f32,
+    /// Percent of max HP the holder loses after it damages another pokemon
+    #[serde(default)]
+    pub recoil: Percent,
+    /// Multiplies a stat, but the holder can only use the first move it used until it leaves the field.
+    #[serde(default)]
+    pub choice: Option<(StatType, f32)>,
+    /// The holder survives a hit from full HP with 1 HP, using up the item.
+    #[serde(default)]
+    pub sash: bool,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Berry {
+    /// Percent of max HP the holder's HP has to fall to
+    pub threshold: Percent,
+    /// Percent of max HP healed
+    pub heal: Percent,
+}
+
+pub type EngineHeldItems = HashMap<ItemId, HeldItem>;
+
+impl Berry {
+    /// HP healed if the pokemon would eat the berry.
+    pub fn eat(&self, pokemon: &BattlePokemon) -> Option<Health> {
+        let max = pokemon.max_hp() as u32;
+        match !pokemon.fainted() && (pokemon.hp() as u32) * 100 <= max * self.threshold as u32 {
+            true => Some((max * self.heal as u32 / 100).max(1) as Health),
+            false => None,
+        }
+    }
+}
+
+const fn one() -> f32 {
+    1.0
+}
@@ -2,4 +2,7 @@ mod execution;
 pub use execution::*;
 
 mod usage;
-pub use usage::*;
\ No newline at end of file
+pub use usage::*;
+
+mod held;
+pub use held::*;
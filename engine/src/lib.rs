@@ -11,15 +11,28 @@ use hashbrown::HashMap;
 use rand::Rng;
 
 use battle::{
-    data::BattleData,
+    data::{BattleData, FieldEvent, WeatherEvent},
     engine::*,
-    moves::{BattleMove, DamageResult, ClientDamage},
-    pokedex::{item::ItemId, moves::MoveId, pokemon::Health},
-    pokemon::{ActivePosition, Indexed, TeamIndex, BattlePokemon, throw_move},
+    moves::{BattleMove, DamageResult, ClientDamage, MoveCategory, FAILED},
+    pokedex::{
+        ailment::{Ailment, AilmentLength},
+        item::ItemId,
+        moves::MoveId,
+        pokemon::{
+            stat::{BaseStat, StatType},
+            Health,
+        },
+        types::PokemonType,
+    },
+    pokemon::{
+        volatile::{VolatileEvent, VolatileStatus},
+        ActivePosition, Indexed, TeamIndex, BattlePokemon, throw_move,
+    },
     select::*,
+    side::{Hazard, SideCondition, SideEvent},
 };
 
-use crate::scripting::ScriptingEngine;
+use crate::scripting::{ScriptingEngine, Trigger, TriggerSource};
 
 pub mod item;
 mod target;
@@ -30,10 +43,22 @@ use self::moves::*;
 
 pub mod scripting;
 
+pub mod field;
+use self::field::*;
+
+pub mod ability;
+use self::ability::*;
+
+pub mod effects;
+use self::effects::Effects;
+
+pub mod loader;
+
 pub type EngineItems = HashMap<ItemId, BattleItemExecution>;
 pub type EngineMoves = HashMap<MoveId, EngineMove>;
 
 pub type DefaultEngine<ID, T> = DefaultBattleEngine<ID, T, scripting::RhaiScriptingEngine>;
+pub type NativeEngine<ID, T> = DefaultBattleEngine<ID, T, scripting::NativeScriptingEngine<ID>>;
 
 pub struct DefaultBattleEngine<
     ID: Clone + Hash + Eq + Send + Sync + 'static,
@@ -42,6 +67,9 @@ pub struct DefaultBattleEngine<
 > {
     pub items: EngineItems,
     pub moves: EngineMoves,
+    pub fields: EngineFields,
+    pub abilities: EngineAbilities,
+    pub held: EngineHeldItems,
     pub scripting: S,
     _p: PhantomData<(ID, T)>,
 }
@@ -56,6 +84,9 @@ impl<
         Self {
             items: Default::default(),
             moves: Default::default(),
+            fields: Default::default(),
+            abilities: Default::default(),
+            held: Default::default(),
             scripting,
             _p: Default::default(),
         }
@@ -68,6 +99,13 @@ ID: Clone + Hash + Eq + Send + Sync + 'static,
 T: Send + Sync + 'static,
 S: ScriptingEngine<ID, T> + Send + Sync> {
     scripting: S::Data,
+    field: Field,
+    /// Moves pokemon are locked into by their held item
+    choice: HashMap<TeamIndex<ID>, MoveId>,
+    /// Pokemon seeded by Leech Seed and the positions that heal from them
+    seeds: HashMap<TeamIndex<ID>, TeamIndex<ID>>,
+    /// Pokemon in the middle of a multi-turn move
+    turns: HashMap<TeamIndex<ID>, MoveState<ID>>,
     _p: PhantomData<(ID, T)>,
 }
 
@@ -78,7 +116,10 @@ impl<ID: Clone + Hash + Eq + Send + Sync + 'static, T: Send + Sync + 'static>
         Self {
             items: Default::default(),
             moves: Default::default(),
-            scripting: scripting::RhaiScriptingEngine::new::<ID, R>(),
+            fields: Default::default(),
+            abilities: Default::default(),
+            held: Default::default(),
+            scripting: scripting::RhaiScriptingEngine::new::<R>(),
             _p: Default::default(),
         }
     }
@@ -89,7 +130,7 @@ ID: Clone + Hash + Eq + Send + Sync + 'static,
 T: Send + Sync + 'static,
 S: ScriptingEngine<ID, T> + Send + Sync> Default for DefaultEngineData<ID, T, S> {
     fn default() -> Self {
-        Self { scripting: Default::default(), _p: Default::default() }
+        Self { scripting: Default::default(), field: Default::default(), choice: Default::default(), seeds: Default::default(), turns: Default::default(), _p: Default::default() }
     }
 }
 
@@ -116,6 +157,18 @@ impl<
         match selection {
             BattleSelection::Move(id, ..) => {
                 if let Some(pokemon) = player.party.active(active) {
+                    let locked = data
+                        .choice
+                        .get(&TeamIndex(player.id().clone(), active))
+                        .filter(|_| {
+                            self.effects(&data.field)
+                                .held(pokemon)
+                                .map(|(_, item)| item.choice.is_some())
+                                .unwrap_or_default()
+                        });
+                    if locked.map(|m| m != id).unwrap_or_default() {
+                        return SelectMessage::Request(Some(SelectReason::Locked));
+                    }
                     if let Some(m) = pokemon.moves.iter().find(|m| m.id() == id) {
                         if m.is_empty() {
                             return SelectMessage::Request(Some(SelectReason::NoPP));
@@ -151,15 +204,78 @@ impl<
                 Some(m) => {
                     let targets = target::create_targets(&players, user, &m.data, targeting, random);
 
+                    let mut results = Vec::<Indexed<ID, PublicAction>>::new();
+
+                    if let Some(MoveState::Recharging(..)) = data.turns.get(user) {
+                        data.turns.remove(user);
+                        results.push(Indexed(user.clone(), PublicAction::Recharge));
+                        return Ok(results);
+                    }
+
+                    let effects = self.effects(&data.field);
+
+                    let standing = standing(players);
+
+                    if !before_move(random, battle, effects, user, players, &mut results) {
+                        // being stopped ends a charging move or a rampage
+                        data.turns.remove(user);
+                        self.fainted(&mut data.scripting, random, battle, effects, standing, Some(user), players, &mut results)?;
+                        return Ok(results);
+                    }
+
+                    let state = data.turns.remove(user);
+
+                    if let Some(MultiTurn::Charge(invulnerable)) = m.turns {
+                        if !matches!(state, Some(MoveState::Charging(..))) {
+                            data.turns.insert(user.clone(), MoveState::Charging(*id, targeting.cloned(), invulnerable));
+                            results.push(Indexed(user.clone(), PublicAction::Charge));
+                            return Ok(results);
+                        }
+                    }
+
+                    // semi-invulnerable pokemon dodge moves that cannot reach them
+                    let targets = targets
+                        .into_iter()
+                        .filter(|target| {
+                            match data.turns.get(target).and_then(MoveState::invulnerable) {
+                                Some(invulnerable) if !m.reaches.contains(&invulnerable) => {
+                                    results.push(Indexed(user.clone(), PublicAction::Miss));
+                                    false
+                                }
+                                _ => true,
+                            }
+                        })
+                        .collect::<Vec<_>>();
+
+                    let mut bmove = m.data.clone();
+                    bmove.accuracy = data.field.accuracy(bmove.accuracy);
+
+                    self.trigger(&mut data.scripting, random, battle, effects, Trigger::BeforeMove, user, targets.first(), players, &mut results)?;
+
                     match &m.usage {
                         MoveExecution::Actions(actions) => {
-                            let mut results = Vec::<Indexed<ID, PublicAction>>::new();
-                            for target_id in targets {
+                            let user_modifiers = players
+                                .get(user.team())
+                                .and_then(|p| p.party.active(user.index()).map(|u| (u, &p.party.side)))
+                                .map(|(u, side)| effects.modifiers(battle, side, u))
+                                .unwrap_or_default();
+
+                            // effects on the user happen after the move hits every target
+                            let mut deferred = Vec::new();
+
+                            for target_id in targets.iter().cloned() {
                                 match players.get(target_id.team())
-                                    .and_then(|p| p.party.active(target_id.index()))
+                                    .and_then(|p| p.party.active(target_id.index()).map(|t| (t, &p.party.side)))
                                 {
-                                    Some(target) => match throw_move(random, m.data.accuracy) {
+                                    Some((target, side)) => match throw_move(random, bmove.accuracy) {
                                         true => {
+                                            if bmove.category != MoveCategory::Status {
+                                                if let Some(ability) = effects.immune(target, bmove.pokemon_type) {
+                                                    results.push(Indexed(target_id, PublicAction::Ability(*ability)));
+                                                    continue;
+                                                }
+                                            }
+                                            let modifiers = effects.modifiers(battle, side, target).attacker(&user_modifiers);
                                             let mut a = Vec::new();
                                             move_usage(
                                                 &Indexed(
@@ -171,11 +287,18 @@ impl<
                                                 random,
                                                 &mut a,
                                                 &actions,
-                                                &m.data,
+                                                &bmove,
                                                 Indexed(target_id, target),
+                                                &modifiers,
                                             );
                                             for action in a {
-                                                run_action(action, battle, user, &mut results, players);
+                                                if !on_field(players, &action.0) {
+                                                    continue;
+                                                }
+                                                match &action.0 == user {
+                                                    true => deferred.push(action),
+                                                    false => run_action(action, random, battle, effects, user, &mut results, players),
+                                                }
                                             }
                                         }
                                         false => {
@@ -185,16 +308,94 @@ impl<
                                     None => unreachable!(),
                                 }
                             }
-                            Ok(results)
+
+                            for action in deferred {
+                                if on_field(players, &action.0) {
+                                    run_action(action, random, battle, effects, user, &mut results, players);
+                                }
+                            }
                         }
                         MoveExecution::Script => {
-                            return self
+                            results.extend(self
                                 .scripting
-                                .execute_move(&mut data.scripting, random, battle, &m.data, user, targets, players)
-                                .map_err(DefaultError::Script);
+                                .execute_move(&mut data.scripting, random, battle, effects, &bmove, user, targets.clone(), players)
+                                .map_err(DefaultError::Script)?);
                         }
-                        MoveExecution::None => Err(DefaultError::Unimplemented),
+                        MoveExecution::None => return Err(DefaultError::Unimplemented),
                     }
+
+                    let mut damaged = false;
+
+                    for target in targets.iter() {
+                        let hit = target != user && results.iter().any(|Indexed(t, a)| {
+                            t == target && matches!(a, PublicAction::SetHP(ClientDamage::Result(..)))
+                        });
+
+                        // damage to a substitute still counts as damage dealt
+                        let blocked = target != user && results.iter().any(|Indexed(t, a)| {
+                            t == target && matches!(a, PublicAction::Volatile(VolatileEvent::Blocked | VolatileEvent::End(VolatileStatus::Substitute)))
+                        });
+
+                        damaged |= hit || blocked;
+
+                        if hit {
+                            self.trigger(&mut data.scripting, random, battle, effects, Trigger::DamageTaken, target, Some(user), players, &mut results)?;
+                            if m.data.contact.0 {
+                                self.trigger(&mut data.scripting, random, battle, effects, Trigger::Contact, target, Some(user), players, &mut results)?;
+                                contact(random, battle, effects, target, user, players, &mut results);
+                            }
+                        }
+                    }
+
+                    for Indexed(target, action) in results.iter() {
+                        if let PublicAction::Volatile(VolatileEvent::Start(VolatileStatus::LeechSeed)) = action {
+                            data.seeds.insert(target.clone(), user.clone());
+                        }
+                    }
+
+                    match m.turns {
+                        Some(MultiTurn::Recharge) => {
+                            data.turns.insert(user.clone(), MoveState::Recharging(*id));
+                        }
+                        Some(MultiTurn::Rampage) => {
+                            let left = match state {
+                                Some(MoveState::Rampage(.., left)) => left.saturating_sub(1),
+                                _ => random.gen_range(1..=2),
+                            };
+                            match left {
+                                0 => {
+                                    if on_field(players, user) {
+                                        run_action(Indexed(user.clone(), ActionResult::Volatile(VolatileStatus::Confusion, true)), random, battle, effects, user, &mut results, players);
+                                    }
+                                }
+                                left => {
+                                    data.turns.insert(user.clone(), MoveState::Rampage(*id, targeting.cloned(), left));
+                                }
+                            }
+                        }
+                        _ => (),
+                    }
+
+                    let held = players
+                        .get(user.team())
+                        .and_then(|p| p.party.active(user.index()))
+                        .and_then(|p| effects.held(p).map(|(id, item)| (p, id, item)));
+
+                    if let Some((pokemon, item_id, item)) = held {
+                        if item.choice.is_some() {
+                            data.choice.entry(user.clone()).or_insert(*id);
+                        }
+
+                        if damaged && item.recoil != 0 {
+                            let recoil = (pokemon.max_hp() as u32 * item.recoil as u32 / 100).max(1) as i16;
+                            results.push(Indexed(user.clone(), PublicAction::Item(*item_id, false)));
+                            run_action(Indexed(user.clone(), ActionResult::Heal(-recoil)), random, battle, effects, user, &mut results, players);
+                        }
+                    }
+
+                    self.fainted(&mut data.scripting, random, battle, effects, standing, Some(user), players, &mut results)?;
+
+                    Ok(self.update_field(&mut data.field, results))
                 }
                 None => Err(DefaultError::Unknown),
             },
@@ -213,10 +414,11 @@ impl<
                         Err(DefaultError::Unimplemented)
                     }
                     BattleItemExecution::Script => {
-                        return self
+                        let results = self
                             .scripting
-                            .execute_item(&mut data.scripting, random, battle, id, user, target, players)
-                            .map_err(DefaultError::Script);
+                            .execute_item(&mut data.scripting, random, battle, self.effects(&data.field), id, user, target, players)
+                            .map_err(DefaultError::Script)?;
+                        Ok(self.update_field(&mut data.field, results))
                     }
                     BattleItemExecution::Pokeball => match battle.versus.is_wild() {
                         true => Ok(match players.iter().find(|p| p.id() == target.team()).and_then(|p| p.party.active(target.index())).is_some() {
@@ -244,11 +446,285 @@ impl<
         battle: &mut BattleData,
         players: &mut PlayerQuery<ID, T>,
     ) -> Result<Vec<Indexed<ID, PublicAction>>, Self::ExecutionError> {
-        Ok(vec![])
+        let mut results = Vec::new();
+
+        let active = players
+            .iter()
+            .flat_map(|p| {
+                p.party
+                    .active_iter()
+                    .map(|(i, ..)| TeamIndex(p.id().clone(), i))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        let effects = self.effects(&data.field);
+
+        let standing = standing(players);
+
+        if let Some(weather) = battle.weather() {
+            for pokemon in active.iter() {
+                let damage = players
+                    .get(pokemon.team())
+                    .and_then(|p| p.party.active(pokemon.index()))
+                    .and_then(|p| p.weather_damage(weather));
+
+                if let Some(damage) = damage {
+                    run_action(Indexed(pokemon.clone(), ActionResult::Heal(-(damage as i16))), random, battle, effects, pokemon, &mut results, players);
+                }
+            }
+        }
+
+        let heal = data.field.heal();
+
+        if heal != 0 {
+            for pokemon in active.iter() {
+                let health = players
+                    .get(pokemon.team())
+                    .and_then(|p| p.party.active(pokemon.index()))
+                    .filter(|p| p.grounded() && !p.fainted() && p.hp() < p.max_hp())
+                    .map(|p| (p.max_hp() as u32 * heal as u32 / 100).max(1) as i16);
+
+                if let Some(health) = health {
+                    run_action(Indexed(pokemon.clone(), ActionResult::Heal(health)), random, battle, effects, pokemon, &mut results, players);
+                }
+            }
+        }
+
+        for pokemon in active.iter() {
+            let heal = players
+                .get(pokemon.team())
+                .and_then(|p| p.party.active(pokemon.index()))
+                .filter(|p| !p.fainted() && p.hp() < p.max_hp())
+                .and_then(|p| {
+                    effects
+                        .held(p)
+                        .filter(|(_, item)| item.heal != 0)
+                        .map(|(id, item)| (*id, (p.max_hp() as u32 * item.heal as u32 / 100).max(1) as i16))
+                });
+
+            if let Some((item, health)) = heal {
+                results.push(Indexed(pokemon.clone(), PublicAction::Item(item, false)));
+                run_action(Indexed(pokemon.clone(), ActionResult::Heal(health)), random, battle, effects, pokemon, &mut results, players);
+            }
+        }
+
+        for pokemon in active.iter() {
+            let damage = players
+                .get_mut(pokemon.team())
+                .and_then(|p| p.party.active_mut(pokemon.index()))
+                .filter(|p| !p.fainted())
+                .and_then(|p| {
+                    let damage = p.ailment.map(|a| a.ailment).zip(p.ailment_damage());
+                    if let Some(toxic) = p.ailment_counter.toxic.as_mut() {
+                        *toxic = toxic.saturating_add(1);
+                    }
+                    damage
+                });
+
+            if let Some((ailment, damage)) = damage {
+                results.push(Indexed(pokemon.clone(), PublicAction::Hurt(ailment)));
+                run_action(Indexed(pokemon.clone(), ActionResult::Heal(-(damage as i16))), random, battle, effects, pokemon, &mut results, players);
+            }
+        }
+
+        for pokemon in active.iter() {
+            let volatile = players
+                .get(pokemon.team())
+                .and_then(|p| p.party.active(pokemon.index()))
+                .filter(|p| !p.fainted())
+                .map(|p| (p.max_hp(), p.volatile.clone()));
+
+            let (max, volatile) = match volatile {
+                Some(volatile) => volatile,
+                None => continue,
+            };
+
+            if volatile.seeded {
+                let damage = (max / 8).max(1) as i16;
+                results.push(Indexed(pokemon.clone(), PublicAction::Volatile(VolatileEvent::Hurt(VolatileStatus::LeechSeed))));
+                run_action(Indexed(pokemon.clone(), ActionResult::Heal(-damage)), random, battle, effects, pokemon, &mut results, players);
+                if let Some(seeder) = data.seeds.get(pokemon).filter(|seeder| on_field(players, seeder)) {
+                    run_action(Indexed(seeder.clone(), ActionResult::Heal(damage)), random, battle, effects, seeder, &mut results, players);
+                }
+            }
+
+            if volatile.cursed && on_field(players, pokemon) {
+                let damage = (max / 4).max(1) as i16;
+                results.push(Indexed(pokemon.clone(), PublicAction::Volatile(VolatileEvent::Hurt(VolatileStatus::Curse))));
+                run_action(Indexed(pokemon.clone(), ActionResult::Heal(-damage)), random, battle, effects, pokemon, &mut results, players);
+            }
+
+            if let Some(turns) = volatile.perish.filter(|_| on_field(players, pokemon)) {
+                let turns = turns.saturating_sub(1);
+                if let Some(p) = players.get_mut(pokemon.team()).and_then(|p| p.party.active_mut(pokemon.index())) {
+                    p.volatile.perish = Some(turns);
+                }
+                results.push(Indexed(pokemon.clone(), PublicAction::Volatile(VolatileEvent::Perish(turns))));
+                if turns == 0 {
+                    run_action(Indexed(pokemon.clone(), ActionResult::Heal(-(max as i16))), random, battle, effects, pokemon, &mut results, players);
+                }
+            }
+        }
+
+        for pokemon in active.iter() {
+            if let Some(p) = players.get_mut(pokemon.team()).and_then(|p| p.party.active_mut(pokemon.index())) {
+                p.volatile.flinch = false;
+            }
+        }
+
+        for pokemon in active.iter() {
+            self.trigger(&mut data.scripting, random, battle, effects, Trigger::TurnEnd, pokemon, None, players, &mut results)?;
+        }
+
+        self.fainted(&mut data.scripting, random, battle, effects, standing, None, players, &mut results)?;
+
+        if let Some(weather) = battle.weather.as_mut() {
+            if !weather.tick() {
+                battle.weather = None;
+            }
+        }
+
+        let mut results = self.update_field(&mut data.field, results);
+
+        data.field.tick();
+
+        for player in players.iter_mut() {
+            // side events are reported through a pokemon on the field, so a side without one waits a turn
+            let index = match player.party.active_iter().next() {
+                Some((index, ..)) => index,
+                None => continue,
+            };
+            let id = player.id().clone();
+            for condition in player.party.side.tick() {
+                results.push(Indexed(TeamIndex(id.clone(), index), PublicAction::Side(SideEvent::End(condition))));
+            }
+        }
+
+        Ok(results)
     }
 
-    fn reset(&self, data: &mut Self::Data) {
+    fn switch_in(
+        &self,
+        data: &mut Self::Data,
+        random: &mut (impl Rng + Clone + Send + Sync + 'static),
+        battle: &mut BattleData,
+        pokemon: &TeamIndex<ID>,
+        players: &mut PlayerQuery<ID, T>,
+    ) -> Result<Vec<Indexed<ID, PublicAction>>, Self::ExecutionError> {
+        let mut results = Vec::new();
+
+        data.choice.remove(pokemon);
+        data.seeds.remove(pokemon);
+        data.turns.remove(pokemon);
+
+        let hazards = players
+            .get(pokemon.team())
+            .and_then(|p| p.party.active(pokemon.index()).map(|a| (a, &p.party.side)))
+            .map(|(active, side)| {
+                let mut hazards = Vec::new();
 
+                let damage = side.hazard_damage(active);
+                if damage != 0 {
+                    hazards.push(ActionResult::Heal(-(damage as i16)));
+                }
+
+                let layers = side.layers(Hazard::ToxicSpikes);
+                if layers != 0 && active.grounded() {
+                    if active.is_type(PokemonType::Poison) {
+                        hazards.push(ActionResult::Side(SideEvent::Clear(Hazard::ToxicSpikes)));
+                    } else if active.ailment.is_none() && !active.is_type(PokemonType::Steel) {
+                        hazards.push(ActionResult::Ailment(Some(AilmentLength::Permanent.init(Ailment::Poison, random))));
+                        // two or more layers badly poison
+                        if layers > 1 {
+                            hazards.push(ActionResult::Toxic);
+                        }
+                    }
+                }
+
+                hazards
+            })
+            .unwrap_or_default();
+
+        let effects = self.effects(&data.field);
+
+        let standing = standing(players);
+
+        for hazard in hazards {
+            if on_field(players, pokemon) {
+                run_action(Indexed(pokemon.clone(), hazard), random, battle, effects, pokemon, &mut results, players);
+            }
+        }
+
+        let ability = players
+            .get(pokemon.team())
+            .and_then(|p| p.party.active(pokemon.index()))
+            .filter(|p| !p.fainted())
+            .and_then(|p| effects.ability(p))
+            .filter(|(_, ability)| !ability.switch_in.is_empty());
+
+        if let Some((id, ability)) = ability {
+            results.push(Indexed(pokemon.clone(), PublicAction::Ability(*id)));
+
+            let opponents = players
+                .iter()
+                .filter(|p| p.id() != pokemon.team())
+                .flat_map(|p| {
+                    p.party
+                        .active_iter()
+                        .map(|(i, ..)| TeamIndex(p.id().clone(), i))
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>();
+
+            for (target, stat, stage) in ability.switch_in.iter() {
+                let targets = match target {
+                    AbilityTarget::User => core::slice::from_ref(pokemon),
+                    AbilityTarget::Opponents => opponents.as_slice(),
+                };
+                for target in targets {
+                    run_action(Indexed(target.clone(), ActionResult::Stat(*stat, *stage)), random, battle, effects, pokemon, &mut results, players);
+                }
+            }
+        }
+
+        self.trigger(&mut data.scripting, random, battle, effects, Trigger::SwitchIn, pokemon, None, players, &mut results)?;
+        self.fainted(&mut data.scripting, random, battle, effects, standing, None, players, &mut results)?;
+        Ok(self.update_field(&mut data.field, results))
+    }
+
+    fn slower_first(&self, data: &Self::Data) -> bool {
+        data.field.slower_first()
+    }
+
+    fn speed(&self, data: &Self::Data, pokemon: &BattlePokemon) -> BaseStat {
+        let multiplier = self
+            .effects(&data.field)
+            .held(pokemon)
+            .and_then(|(_, item)| item.choice)
+            .filter(|(stat, ..)| *stat == StatType::Speed)
+            .map(|(.., multiplier)| multiplier)
+            .unwrap_or(1.0);
+        (pokemon.speed() as f32 * multiplier) as BaseStat
+    }
+
+    fn forced(&self, data: &Self::Data, pokemon: &TeamIndex<ID>) -> Option<BattleSelection<ID>> {
+        data.turns.get(pokemon).map(MoveState::selection)
+    }
+
+    fn recharging(&self, data: &Self::Data, pokemon: &TeamIndex<ID>) -> bool {
+        matches!(data.turns.get(pokemon), Some(MoveState::Recharging(..)))
+    }
+
+    fn field(&self, data: &Self::Data) -> Vec<battle::data::FieldId> {
+        data.field.ids().copied().collect()
+    }
+
+    fn reset(&self, data: &mut Self::Data) {
+        data.field.clear();
+        data.choice.clear();
+        data.seeds.clear();
+        data.turns.clear();
     }
 
     fn get_move(&self, id: &MoveId) -> Option<&BattleMove> {
@@ -257,6 +733,139 @@ impl<
     
 }
 
+impl<
+        ID: Clone + Hash + Eq + Send + Sync + 'static,
+        T: Send + Sync + 'static,
+        S: ScriptingEngine<ID, T> + Send + Sync + 'static,
+    > DefaultBattleEngine<ID, T, S>
+{
+    /// Run the passive effects of an active pokemon for an event.
+    fn trigger(
+        &self,
+        data: &mut S::Data,
+        random: &mut (impl Rng + Clone + Send + Sync + 'static),
+        battle: &mut BattleData,
+        effects: Effects,
+        trigger: Trigger,
+        pokemon: &TeamIndex<ID>,
+        other: Option<&TeamIndex<ID>>,
+        players: &mut PlayerQuery<ID, T>,
+        results: &mut Vec<Indexed<ID, PublicAction>>,
+    ) -> Result<(), DefaultError<S::ExecutionError>> {
+        match players.get(pokemon.team()).and_then(|p| p.party.index(pokemon.index())) {
+            Some(index) => self.trigger_at(data, random, battle, effects, trigger, pokemon, index, other, players, results),
+            None => Ok(()),
+        }
+    }
+
+    /// Run the passive effects of a pokemon at an index in its party for an event.
+    fn trigger_at(
+        &self,
+        data: &mut S::Data,
+        random: &mut (impl Rng + Clone + Send + Sync + 'static),
+        battle: &mut BattleData,
+        effects: Effects,
+        trigger: Trigger,
+        pokemon: &TeamIndex<ID>,
+        index: usize,
+        other: Option<&TeamIndex<ID>>,
+        players: &mut PlayerQuery<ID, T>,
+        results: &mut Vec<Indexed<ID, PublicAction>>,
+    ) -> Result<(), DefaultError<S::ExecutionError>> {
+        let sources = match players.get(pokemon.team()).and_then(|p| p.party.pokemon.get(index)) {
+            Some(p) => TriggerSource::of(p),
+            None => return Ok(()),
+        };
+
+        for source in sources.iter() {
+            results.extend(
+                self.scripting
+                    .execute_trigger(data, random, battle, effects, trigger, source, pokemon, index, other, players)
+                    .map_err(DefaultError::Script)?,
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Run the faint triggers of pokemon that were standing before and have fainted since.
+    /// The other pokemon of the triggers is the one that caused the faints, if any.
+    fn fainted(
+        &self,
+        data: &mut S::Data,
+        random: &mut (impl Rng + Clone + Send + Sync + 'static),
+        battle: &mut BattleData,
+        effects: Effects,
+        standing: Vec<(TeamIndex<ID>, usize)>,
+        other: Option<&TeamIndex<ID>>,
+        players: &mut PlayerQuery<ID, T>,
+        results: &mut Vec<Indexed<ID, PublicAction>>,
+    ) -> Result<(), DefaultError<S::ExecutionError>> {
+        for (pokemon, index) in standing {
+            let fainted = players
+                .get(pokemon.team())
+                .and_then(|p| p.party.pokemon.get(index))
+                .map(|p| p.fainted())
+                .unwrap_or_default();
+            if fainted {
+                let other = other.filter(|other| *other != &pokemon);
+                self.trigger_at(data, random, battle, effects, Trigger::Faint, &pokemon, index, other, players, results)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn effects<'a>(&'a self, field: &'a Field) -> Effects<'a> {
+        Effects {
+            field,
+            abilities: &self.abilities,
+            held: &self.held,
+        }
+    }
+
+    /// Start and end the field conditions in results.
+    /// Conditions that are not registered are left out.
+    fn update_field(
+        &self,
+        field: &mut Field,
+        results: Vec<Indexed<ID, PublicAction>>,
+    ) -> Vec<Indexed<ID, PublicAction>> {
+        let mut updated = Vec::with_capacity(results.len());
+
+        for Indexed(pokemon, action) in results {
+            match action {
+                PublicAction::Field(FieldEvent::Start(id)) => {
+                    if let Some(condition) = self.fields.get(&id) {
+                        match field.contains(&id) {
+                            true => match condition.toggle {
+                                true => {
+                                    field.end(&id);
+                                    updated.push(Indexed(pokemon, PublicAction::Field(FieldEvent::End(id))));
+                                }
+                                false => updated.push(Indexed(pokemon, PublicAction::Cancel(FAILED))),
+                            },
+                            false => updated.extend(
+                                field
+                                    .start(id, condition)
+                                    .into_iter()
+                                    .map(|event| Indexed(pokemon.clone(), PublicAction::Field(event))),
+                            ),
+                        }
+                    }
+                }
+                PublicAction::Field(FieldEvent::End(id)) => {
+                    if field.end(&id) {
+                        updated.push(Indexed(pokemon, action));
+                    }
+                }
+                action => updated.push(Indexed(pokemon, action)),
+            }
+        }
+
+        updated
+    }
+}
+
 #[derive(Debug)]
 pub enum DefaultError<S: Error = NoScriptError> {
     Script(S),
@@ -292,7 +901,9 @@ impl Display for NoScriptError {
 
 fn run_action<ID: PartialEq + Clone, T>(
     Indexed(target_id, action): Indexed<ID, ActionResult>,
-    data: &BattleData,
+    random: &mut impl Rng,
+    data: &mut BattleData,
+    effects: Effects,
     user_id: &TeamIndex<ID>,
     actions: &mut Vec<Indexed<ID, PublicAction>>,
     players: &mut PlayerQuery<ID, T>
@@ -300,6 +911,17 @@ fn run_action<ID: PartialEq + Clone, T>(
     match players.get_mut(target_id.team())
     {
         Some(player) => {
+            if let ActionResult::Side(event) = action {
+                let action = match player.party.side.apply(event) {
+                    true => PublicAction::Side(event),
+                    false => PublicAction::Cancel(FAILED),
+                };
+                actions.push(Indexed(target_id, action));
+                return;
+            }
+
+            let safeguard = player.party.side.has(SideCondition::Safeguard);
+
             match player.party.active_mut(target_id.index()) {
                 Some(target) => {
                     /// calculates hp and adds it to actions
@@ -322,18 +944,68 @@ fn run_action<ID: PartialEq + Clone, T>(
 
                     let t_id = target_id.clone();
 
+                    let prevented = match &action {
+                        ActionResult::Ailment(Some(ailment)) => effects.prevents_ailment(target, ailment.ailment),
+                        _ => None,
+                    };
+
+                    if let Some(ability) = prevented {
+                        actions.push(Indexed(target_id, PublicAction::Ability(*ability)));
+                        return;
+                    }
+
+                    // a substitute takes hits and status effects from other pokemon
+                    if target_id != *user_id {
+                        if let Some(substitute) = target.volatile.substitute.as_mut() {
+                            match &action {
+                                ActionResult::Damage(result) => {
+                                    *substitute = substitute.saturating_sub(result.damage);
+                                    let event = match *substitute == 0 {
+                                        true => {
+                                            target.volatile.end(VolatileStatus::Substitute);
+                                            VolatileEvent::End(VolatileStatus::Substitute)
+                                        }
+                                        false => VolatileEvent::Blocked,
+                                    };
+                                    actions.push(Indexed(target_id, PublicAction::Volatile(event)));
+                                    return;
+                                }
+                                ActionResult::Ailment(Some(..)) | ActionResult::Volatile(_, true) => {
+                                    actions.push(Indexed(target_id, PublicAction::Cancel(FAILED)));
+                                    return;
+                                }
+                                _ => (),
+                            }
+                        }
+                    }
+
                     match action {
-                        ActionResult::Damage(result) => {
-                            on_damage(target_id, target, actions, result)
+                        ActionResult::Damage(mut result) => {
+                            let sash = effects
+                                .held(target)
+                                .filter(|(_, item)| item.sash && target.hp == target.max_hp() && result.damage >= target.hp)
+                                .map(|(id, _)| *id);
+                            if sash.is_some() {
+                                result.damage = target.hp.saturating_sub(1);
+                                target.item = None;
+                            }
+                            on_damage(target_id.clone(), target, actions, result);
+                            if let Some(item) = sash {
+                                actions.push(Indexed(target_id, PublicAction::Item(item, true)));
+                            }
+                        }
+                        ActionResult::Ailment(Some(..)) if safeguard || (target.grounded() && effects.field.prevents_ailments()) => {
+                            actions.push(Indexed(target_id, PublicAction::Cancel(FAILED)));
                         }
                         ActionResult::Ailment(ailment) => {
                             target.ailment = ailment;
+                            target.ailment_counter = Default::default();
                             actions.push(Indexed(target_id, PublicAction::Ailment(ailment)));
                         }
                         ActionResult::Heal(health) => {
                             let hp = health.unsigned_abs();
                             target.hp = match health.is_positive() {
-                                true => target.hp + hp.min(target.max_hp()),
+                                true => (target.hp + hp).min(target.max_hp()),
                                 false => target.hp.saturating_sub(hp),
                             };
                             actions.push(Indexed(
@@ -352,21 +1024,98 @@ fn run_action<ID: PartialEq + Clone, T>(
                         ActionResult::Miss => {
                             actions.push(Indexed(target_id, PublicAction::Miss));
                         }
-                        ActionResult::Reveal(full) => {
-                            todo!();
-                            // player.party.know(index)
+                        ActionResult::Reveal(..) => {
+                            target.reveal();
+                            actions.push(Indexed(target_id, PublicAction::Reveal));
                         }
                         ActionResult::Remove(reason) => {
-                            todo!();
-                            // player.party.remove_active(active);
+                            target.switch_out();
+                            actions.push(Indexed(target_id, PublicAction::Remove(reason)));
+                            player.party.remove_active(t_id.index());
+                            return;
+                        }
+                        ActionResult::Weather(weather) => {
+                            let event = match weather {
+                                Some(weather) => Some(WeatherEvent::Start(weather.weather)),
+                                None => data.weather().map(WeatherEvent::End),
+                            };
+                            data.weather = weather;
+                            if let Some(event) = event {
+                                actions.push(Indexed(target_id, PublicAction::Weather(event)));
+                            }
+                        }
+                        ActionResult::Field(id, start) => {
+                            let event = match start {
+                                true => FieldEvent::Start(id),
+                                false => FieldEvent::End(id),
+                            };
+                            actions.push(Indexed(target_id, PublicAction::Field(event)));
+                        }
+                        // applied to the side above
+                        ActionResult::Side(..) => (),
+                        ActionResult::Toxic => {
+                            if target.has_ailment(Ailment::Poison) {
+                                target.ailment_counter.toxic = Some(1);
+                            }
+                        }
+                        ActionResult::Fail => {
+                            actions.push(Indexed(target_id, PublicAction::Cancel(FAILED)));
+                        }
+                        ActionResult::Volatile(VolatileStatus::Substitute, true) => {
+                            let cost = target.max_hp() / 4;
+                            match target.hp > cost && target.volatile.start(VolatileStatus::Substitute, cost, random) {
+                                true => {
+                                    target.hp -= cost;
+                                    actions.push(Indexed(
+                                        target_id.clone(),
+                                        PublicAction::SetHP(ClientDamage::Number(target.percent_hp())),
+                                    ));
+                                    actions.push(Indexed(
+                                        target_id,
+                                        PublicAction::Volatile(VolatileEvent::Start(VolatileStatus::Substitute)),
+                                    ));
+                                }
+                                false => {
+                                    actions.push(Indexed(target_id, PublicAction::Cancel(FAILED)));
+                                }
+                            }
+                        }
+                        ActionResult::Volatile(status, true) => {
+                            let action = match target.volatile.start(status, 0, random) {
+                                true => PublicAction::Volatile(VolatileEvent::Start(status)),
+                                false => PublicAction::Cancel(FAILED),
+                            };
+                            actions.push(Indexed(target_id, action));
+                        }
+                        ActionResult::Hits(hits) => {
+                            actions.push(Indexed(target_id, PublicAction::Hits(hits)));
+                        }
+                        ActionResult::Volatile(status, false) => {
+                            if target.volatile.end(status) {
+                                actions.push(Indexed(target_id, PublicAction::Volatile(VolatileEvent::End(status))));
+                            }
                         }
-                        ActionResult::Fail => todo!(),
+                    }
+
+                    let berry = effects
+                        .held(target)
+                        .and_then(|(id, item)| item.berry.and_then(|berry| berry.eat(target)).map(|heal| (*id, heal)));
+
+                    if let Some((item, heal)) = berry {
+                        target.hp = (target.hp + heal).min(target.max_hp());
+                        target.item = None;
+                        actions.push(Indexed(t_id.clone(), PublicAction::Item(item, true)));
+                        actions.push(Indexed(
+                            t_id.clone(),
+                            PublicAction::SetHP(ClientDamage::Number(target.percent_hp())),
+                        ));
                     }
 
                     if target.fainted() {
 
                         let experience = target.battle_exp_from(data.versus);
 
+                        target.switch_out();
                         player.party.remove_active(t_id.index());
 
                         if &player.party.id != t_id.team() {
@@ -401,3 +1150,986 @@ fn run_action<ID: PartialEq + Clone, T>(
         None => unreachable!(),
     }
 }
+
+/// If a pokemon is still on the field. Results made before earlier ones were applied
+/// can be for a pokemon that has since fainted, and are dropped instead of run.
+fn on_field<ID: PartialEq, T>(players: &PlayerQuery<ID, T>, pokemon: &TeamIndex<ID>) -> bool {
+    players
+        .get(pokemon.team())
+        .and_then(|p| p.party.active(pokemon.index()))
+        .is_some()
+}
+
+/// Active pokemon that have not fainted, with their index in their party.
+fn standing<ID: PartialEq + Clone, T>(players: &PlayerQuery<ID, T>) -> Vec<(TeamIndex<ID>, usize)> {
+    players
+        .iter()
+        .flat_map(|p| {
+            p.party
+                .active_iter()
+                .filter(|(.., pokemon)| !pokemon.fainted())
+                .flat_map(|(active, ..)| p.party.index(active).map(|index| (TeamIndex(p.id().clone(), active), index)))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Check if a pokemon's ailment and volatile statuses let it move, waking it up or thawing it out if it gets over it.
+fn before_move<ID: PartialEq + Clone, T>(
+    random: &mut impl Rng,
+    data: &mut BattleData,
+    effects: Effects,
+    user: &TeamIndex<ID>,
+    players: &mut PlayerQuery<ID, T>,
+    results: &mut Vec<Indexed<ID, PublicAction>>,
+) -> bool {
+    let pokemon = match players.get_mut(user.team()).and_then(|p| p.party.active_mut(user.index())) {
+        Some(pokemon) => pokemon,
+        None => return true,
+    };
+
+    if let Some(ailment) = pokemon.ailment.map(|a| a.ailment) {
+        let recover = match ailment {
+            Ailment::Sleep => {
+                let turns = pokemon.ailment_counter.sleep.get_or_insert_with(|| random.gen_range(1..=3));
+                match *turns {
+                    0 => Some(true),
+                    _ => {
+                        *turns -= 1;
+                        Some(false)
+                    }
+                }
+            }
+            Ailment::Freeze => Some(random.gen_bool(0.2)),
+            _ => None,
+        };
+
+        // other ailments do not stop the pokemon here
+        if let Some(recover) = recover {
+            match recover {
+                true => {
+                    pokemon.ailment = None;
+                    pokemon.ailment_counter = Default::default();
+                    results.push(Indexed(user.clone(), PublicAction::Recover(ailment)));
+                }
+                false => {
+                    results.push(Indexed(user.clone(), PublicAction::Immobile(ailment)));
+                    return false;
+                }
+            }
+        }
+    }
+
+    if pokemon.volatile.flinch {
+        results.push(Indexed(user.clone(), PublicAction::Volatile(VolatileEvent::Immobile(VolatileStatus::Flinch))));
+        return false;
+    }
+
+    if let Some(turns) = pokemon.volatile.confusion.as_mut() {
+        *turns = turns.saturating_sub(1);
+        if *turns == 0 {
+            pokemon.volatile.end(VolatileStatus::Confusion);
+            results.push(Indexed(user.clone(), PublicAction::Volatile(VolatileEvent::End(VolatileStatus::Confusion))));
+        } else if random.gen_bool(1.0 / 3.0) {
+            let result = pokemon.move_power_damage_random(
+                random,
+                pokemon,
+                40,
+                MoveCategory::Physical,
+                PokemonType::Unknown,
+                false,
+                &Default::default(),
+            );
+            results.push(Indexed(user.clone(), PublicAction::Volatile(VolatileEvent::Hurt(VolatileStatus::Confusion))));
+            run_action(Indexed(user.clone(), ActionResult::Damage(result)), random, data, effects, user, results, players);
+            return false;
+        }
+    }
+
+    if pokemon.has_ailment(Ailment::Paralysis) && random.gen_bool(0.25) {
+        results.push(Indexed(user.clone(), PublicAction::Immobile(Ailment::Paralysis)));
+        return false;
+    }
+
+    if pokemon.volatile.infatuated && random.gen_bool(0.5) {
+        results.push(Indexed(user.clone(), PublicAction::Volatile(VolatileEvent::Immobile(VolatileStatus::Infatuation))));
+        return false;
+    }
+
+    true
+}
+
+/// Run the contact effects of the ability of a pokemon hit by a move.
+fn contact<ID: PartialEq + Clone, T>(
+    random: &mut impl Rng,
+    data: &mut BattleData,
+    effects: Effects,
+    pokemon: &TeamIndex<ID>,
+    attacker: &TeamIndex<ID>,
+    players: &mut PlayerQuery<ID, T>,
+    results: &mut Vec<Indexed<ID, PublicAction>>,
+) {
+    let ability = players
+        .get(pokemon.team())
+        .and_then(|p| p.party.active(pokemon.index()))
+        .and_then(|p| effects.ability(p))
+        .filter(|(_, ability)| !ability.contact.is_empty());
+
+    let (id, ability) = match ability {
+        Some(ability) => ability,
+        None => return,
+    };
+
+    let target = match players.get(attacker.team()).and_then(|p| p.party.active(attacker.index())) {
+        Some(target) => target,
+        None => return,
+    };
+
+    let mut actions = Vec::new();
+
+    for effect in ability.contact.iter() {
+        match *effect {
+            ContactEffect::Ailment(ailment, length, chance) => {
+                if target.ailment.is_none() && random.gen_bool(chance as f64 / 100.0) {
+                    actions.push(ActionResult::Ailment(Some(length.init(ailment, random))));
+                }
+            }
+            ContactEffect::Damage(percent) => {
+                let damage = (target.max_hp() as u32 * percent as u32 / 100).max(1);
+                actions.push(ActionResult::Heal(-(damage as i16)));
+            }
+        }
+    }
+
+    if !actions.is_empty() {
+        results.push(Indexed(pokemon.clone(), PublicAction::Ability(*id)));
+        for action in actions {
+            if on_field(players, attacker) {
+                run_action(Indexed(attacker.clone(), action), random, data, effects, pokemon, results, players);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::{collections::BTreeMap, sync::Arc};
+
+    use rand::rngs::mock::StepRng;
+
+    use battle::{
+        data::BattleData,
+        endpoint::create,
+        engine::{ActionResult, BattleEngine, BattlePlayer, ExecuteAction, PlayerQuery},
+        host::{moves::queue_player, PlayerData},
+        moves::{BattleMove, ClientDamage, Contact, DamageKind, MoveCategory, MoveTarget},
+        player::PlayerSettings,
+        pokedex::{
+            ailment::{Ailment, AilmentLength},
+            item::{Item, ItemId},
+            moves::{set::OwnedMoveSet, MoveId},
+            pokemon::{
+                data::{Breeding, Gender, GrowthRate, Training},
+                owned::OwnedPokemon,
+                stat::{StatSet, StatType},
+                Health, Nature, Pokemon, PokemonId,
+            },
+            types::{PokemonType, PokemonTypes},
+        },
+        pokemon::{stat::BattleStatType, AbilityId, Indexed, TeamIndex},
+        select::{BattleSelection, PublicAction},
+        side::{SideCondition, SideEvent},
+    };
+
+    use crate::{
+        item::{Berry, HeldItem},
+        moves::{EngineMove, MoveExecution, MoveUse, MultiTurn, SemiInvulnerable},
+        scripting::{RhaiScriptingEngine, TriggerSource},
+    };
+
+    use super::{run_action, DefaultEngine, DefaultEngineData, FieldCondition};
+
+    type Engine = DefaultEngine<u8, ()>;
+    type Data = DefaultEngineData<u8, (), RhaiScriptingEngine>;
+
+    fn pokemon() -> OwnedPokemon {
+        let pokemon = Arc::new(Pokemon {
+            id: PokemonId(1),
+            name: "Bulbasaur".to_owned(),
+            types: PokemonTypes {
+                primary: PokemonType::Grass,
+                secondary: Some(PokemonType::Poison),
+            },
+            moves: vec![],
+            base: StatSet::uniform(50),
+            species: "Seed".to_owned(),
+            evolution: None,
+            height: 7,
+            weight: 69,
+            training: Training {
+                base_exp: 64,
+                growth: GrowthRate::MediumSlow,
+            },
+            breeding: Breeding { gender: Some(1) },
+        });
+
+        let mut pokemon = OwnedPokemon {
+            pokemon,
+            level: 50,
+            gender: Gender::Male,
+            nature: Nature::Hardy,
+            hp: 0,
+            ivs: StatSet::uniform(0),
+            evs: StatSet::uniform(0),
+            friendship: Pokemon::default_friendship(),
+            ailment: None,
+            nickname: None,
+            moves: OwnedMoveSet::default(),
+            item: None,
+            experience: 0,
+        };
+
+        pokemon.heal_hp(None);
+
+        pokemon
+    }
+
+    pub(crate) fn player(id: u8, ability: Option<AbilityId>) -> BattlePlayer<u8, ()> {
+        let player = PlayerData {
+            id,
+            name: None,
+            party: [pokemon()].into_iter().collect(),
+            abilities: vec![ability],
+            bag: Default::default(),
+            trainer: None,
+            settings: PlayerSettings { gains_exp: false },
+            endpoint: Arc::new(create().1),
+        }
+        .init(1);
+        player.ready();
+        player
+    }
+
+    pub(crate) fn players() -> PlayerQuery<u8, ()> {
+        PlayerQuery::new(vec![player(1, None), player(2, None)])
+    }
+
+    /// Add a move that deals a little damage to one opponent.
+    fn insert(engine: &mut Engine, id: &str, turns: Option<MultiTurn>) -> MoveId {
+        let id = id.parse().unwrap();
+        engine.moves.insert(
+            id,
+            EngineMove {
+                data: BattleMove {
+                    id,
+                    category: MoveCategory::Physical,
+                    pokemon_type: PokemonType::Normal,
+                    accuracy: None,
+                    power: None,
+                    priority: 0,
+                    target: MoveTarget::Opponent,
+                    contact: Contact(false),
+                    crit_rate: 0,
+                },
+                usage: MoveExecution::Actions(vec![MoveUse::Damage(DamageKind::Constant(1))]),
+                turns,
+                reaches: Vec::new(),
+            },
+        );
+        id
+    }
+
+    fn execute(
+        engine: &Engine,
+        data: &mut Data,
+        players: &mut PlayerQuery<u8, ()>,
+        id: &MoveId,
+        user: &TeamIndex<u8>,
+        target: &TeamIndex<u8>,
+    ) -> Vec<Indexed<u8, PublicAction>> {
+        engine
+            .execute(
+                data,
+                &mut StepRng::new(0, 0),
+                &mut BattleData::default(),
+                ExecuteAction::Move(id, user, Some(target)),
+                players,
+            )
+            .unwrap()
+    }
+
+    fn hit(results: &[Indexed<u8, PublicAction>], target: &TeamIndex<u8>) -> bool {
+        results.iter().any(|Indexed(pokemon, action)| {
+            pokemon == target && matches!(action, PublicAction::SetHP(ClientDamage::Result(..)))
+        })
+    }
+
+    #[test]
+    fn faint_at_turn_end() {
+        let ability = "aftermath".parse().unwrap();
+
+        let mut engine = Engine::new::<StepRng>();
+        engine
+            .scripting
+            .register_trigger(
+                TriggerSource::Ability(ability),
+                r#"fn on_faint(pokemon, other) { [Side(pokemon, "safeguard")] }"#,
+            )
+            .unwrap();
+
+        let mut players = PlayerQuery::new(vec![player(1, Some(ability)), player(2, None)]);
+
+        let cursed = players
+            .get_mut(&1)
+            .and_then(|p| p.party.active_mut(0))
+            .unwrap();
+        cursed.hp = 1;
+        cursed.volatile.cursed = true;
+
+        let results = engine
+            .post(
+                &mut Default::default(),
+                &mut StepRng::new(0, 0),
+                &mut BattleData::default(),
+                &mut players,
+            )
+            .unwrap();
+
+        assert!(results
+            .iter()
+            .any(|Indexed(pokemon, action)| pokemon == &TeamIndex(1, 0)
+                && matches!(
+                    action,
+                    PublicAction::Side(SideEvent::Start(SideCondition::Safeguard))
+                )));
+
+        let side = &players.get(&1).unwrap().party.side;
+        assert!(side.has(SideCondition::Safeguard));
+    }
+
+    #[test]
+    fn charge() {
+        let mut engine = Engine::new::<StepRng>();
+        let id = insert(&mut engine, "solarbeam", Some(MultiTurn::Charge(None)));
+
+        let mut data = Data::default();
+        let mut players = players();
+        let (user, target) = (TeamIndex(1, 0), TeamIndex(2, 0));
+
+        let results = execute(&engine, &mut data, &mut players, &id, &user, &target);
+        assert!(results
+            .iter()
+            .any(|Indexed(pokemon, action)| pokemon == &user
+                && matches!(action, PublicAction::Charge)));
+        assert!(!hit(&results, &target));
+        assert_eq!(
+            engine.forced(&data, &user),
+            Some(BattleSelection::Move(id, Some(target.clone())))
+        );
+
+        let results = execute(&engine, &mut data, &mut players, &id, &user, &target);
+        assert!(hit(&results, &target));
+        assert_eq!(engine.forced(&data, &user), None);
+    }
+
+    #[test]
+    fn invulnerable() {
+        let mut engine = Engine::new::<StepRng>();
+        let fly = insert(
+            &mut engine,
+            "fly",
+            Some(MultiTurn::Charge(Some(SemiInvulnerable::Flying))),
+        );
+        let tackle = insert(&mut engine, "tackle", None);
+        let gust = insert(&mut engine, "gust", None);
+        engine
+            .moves
+            .get_mut(&gust)
+            .unwrap()
+            .reaches
+            .push(SemiInvulnerable::Flying);
+
+        let mut data = Data::default();
+        let mut players = players();
+        let (flying, other) = (TeamIndex(1, 0), TeamIndex(2, 0));
+
+        execute(&engine, &mut data, &mut players, &fly, &flying, &other);
+
+        let results = execute(&engine, &mut data, &mut players, &tackle, &other, &flying);
+        assert!(results
+            .iter()
+            .any(|Indexed(pokemon, action)| pokemon == &other
+                && matches!(action, PublicAction::Miss)));
+        assert!(!hit(&results, &flying));
+
+        let results = execute(&engine, &mut data, &mut players, &gust, &other, &flying);
+        assert!(hit(&results, &flying));
+    }
+
+    #[test]
+    fn rampage() {
+        let mut engine = Engine::new::<StepRng>();
+        let id = insert(&mut engine, "outrage", Some(MultiTurn::Rampage));
+
+        let mut data = Data::default();
+        let mut players = players();
+        let (user, target) = (TeamIndex(1, 0), TeamIndex(2, 0));
+
+        let confused = |players: &PlayerQuery<u8, ()>| {
+            players
+                .get(&1)
+                .and_then(|p| p.party.active(0))
+                .map(|p| p.volatile.confusion.is_some())
+                .unwrap()
+        };
+
+        // the mock random rolls the shortest rampage of two turns
+        let results = execute(&engine, &mut data, &mut players, &id, &user, &target);
+        assert!(hit(&results, &target));
+        assert_eq!(
+            engine.forced(&data, &user),
+            Some(BattleSelection::Move(id, Some(target.clone())))
+        );
+        assert!(!confused(&players));
+
+        let results = execute(&engine, &mut data, &mut players, &id, &user, &target);
+        assert!(hit(&results, &target));
+        assert_eq!(engine.forced(&data, &user), None);
+        assert!(confused(&players));
+    }
+
+    #[test]
+    fn recharge() {
+        let mut engine = Engine::new::<StepRng>();
+        let id = insert(&mut engine, "hyperbeam", Some(MultiTurn::Recharge));
+
+        let mut data = Data::default();
+        let mut players = players();
+        let (user, target) = (TeamIndex(1, 0), TeamIndex(2, 0));
+
+        let results = execute(&engine, &mut data, &mut players, &id, &user, &target);
+        assert!(hit(&results, &target));
+        assert!(engine.recharging(&data, &user));
+
+        let results = execute(&engine, &mut data, &mut players, &id, &user, &target);
+        assert!(!hit(&results, &target));
+        assert!(results
+            .iter()
+            .any(|Indexed(pokemon, action)| pokemon == &user
+                && matches!(action, PublicAction::Recharge)));
+        assert!(!engine.recharging(&data, &user));
+        assert_eq!(engine.forced(&data, &user), None);
+    }
+
+    #[test]
+    fn scripts_see_current_values() {
+        let mut engine = Engine::new::<StepRng>();
+        let id = insert(&mut engine, "superfang", None);
+        engine.moves.get_mut(&id).unwrap().usage = MoveExecution::Script;
+        engine
+            .scripting
+            .register_move(
+                id,
+                r#"
+                fn use_move(m, user, targets) {
+                    let results = [];
+                    for target in targets {
+                        results.push(Heal(target, -(target.hp / 2)));
+                    }
+                    results
+                }
+                "#,
+            )
+            .unwrap();
+
+        let mut data = Data::default();
+        let mut players = players();
+        let (user, target) = (TeamIndex(1, 0), TeamIndex(2, 0));
+
+        let hp = |players: &PlayerQuery<u8, ()>| {
+            players
+                .get(&2)
+                .and_then(|p| p.party.active(0))
+                .map(|p| p.hp)
+                .unwrap()
+        };
+
+        let mut expected = hp(&players);
+
+        // a stale snapshot would halve the full HP again and make the target faint
+        for _ in 0..2 {
+            execute(&engine, &mut data, &mut players, &id, &user, &target);
+            expected -= expected / 2;
+            assert_eq!(hp(&players), expected);
+        }
+
+        assert!(expected > 1);
+    }
+
+    fn held() -> HeldItem {
+        HeldItem {
+            heal: 0,
+            berry: None,
+            attack: Vec::new(),
+            damage: 1.0,
+            recoil: 0,
+            choice: None,
+            sash: false,
+        }
+    }
+
+    /// Give the pokemon at a position a held item.
+    fn hold(
+        engine: &mut Engine,
+        players: &mut PlayerQuery<u8, ()>,
+        pokemon: &TeamIndex<u8>,
+        id: &str,
+        item: HeldItem,
+    ) -> ItemId {
+        let item_id = id.parse().unwrap();
+        engine.held.insert(item_id, item);
+        let item: Item = ron::from_str(&format!(
+            r#"(id: "{}", name: "{}", description: "")"#,
+            id, id
+        ))
+        .unwrap();
+        if let Some(pokemon) = players
+            .get_mut(pokemon.team())
+            .and_then(|p| p.party.active_mut(pokemon.index()))
+        {
+            pokemon.item = Some(item.into());
+        }
+        item_id
+    }
+
+    fn active(players: &PlayerQuery<u8, ()>, pokemon: &TeamIndex<u8>) -> (Health, Health, bool) {
+        players
+            .get(pokemon.team())
+            .and_then(|p| p.party.active(pokemon.index()))
+            .map(|p| (p.hp, p.max_hp(), p.item.is_some()))
+            .unwrap()
+    }
+
+    fn used(
+        results: &[Indexed<u8, PublicAction>],
+        pokemon: &TeamIndex<u8>,
+        item: ItemId,
+        consumed: bool,
+    ) -> bool {
+        results.iter().any(|Indexed(p, action)| {
+            p == pokemon
+                && matches!(action, PublicAction::Item(id, c) if *id == item && *c == consumed)
+        })
+    }
+
+    #[test]
+    fn life_orb() {
+        let mut engine = Engine::new::<StepRng>();
+        let id = insert(&mut engine, "tackle", None);
+
+        let mut data = Data::default();
+        let mut players = players();
+        let (user, target) = (TeamIndex(1, 0), TeamIndex(2, 0));
+
+        let orb = hold(
+            &mut engine,
+            &mut players,
+            &user,
+            "lifeorb",
+            HeldItem {
+                recoil: 10,
+                ..held()
+            },
+        );
+        let (hp, max, ..) = active(&players, &user);
+        let recoil = max / 10;
+
+        let results = execute(&engine, &mut data, &mut players, &id, &user, &target);
+        assert!(hit(&results, &target));
+        assert!(used(&results, &user, orb, false));
+        assert_eq!(active(&players, &user).0, hp - recoil);
+
+        // hitting a substitute is still dealing damage
+        if let Some(pokemon) = players.get_mut(&2).and_then(|p| p.party.active_mut(0)) {
+            pokemon.volatile.substitute = Some(20);
+        }
+
+        let results = execute(&engine, &mut data, &mut players, &id, &user, &target);
+        assert!(!hit(&results, &target));
+        assert!(used(&results, &user, orb, false));
+        assert_eq!(active(&players, &user).0, hp - 2 * recoil);
+    }
+
+    #[test]
+    fn focus_sash() {
+        let mut engine = Engine::new::<StepRng>();
+        let id = insert(&mut engine, "explosion", None);
+        engine.moves.get_mut(&id).unwrap().usage =
+            MoveExecution::Actions(vec![MoveUse::Damage(DamageKind::Constant(1000))]);
+
+        let mut data = Data::default();
+        let mut players = players();
+        let (user, target) = (TeamIndex(1, 0), TeamIndex(2, 0));
+
+        let sash = hold(
+            &mut engine,
+            &mut players,
+            &target,
+            "focussash",
+            HeldItem {
+                sash: true,
+                ..held()
+            },
+        );
+
+        let results = execute(&engine, &mut data, &mut players, &id, &user, &target);
+        assert!(used(&results, &target, sash, true));
+        assert_eq!(active(&players, &target).0, 1);
+        assert!(!active(&players, &target).2);
+
+        // the sash is gone, so the next hit faints the target
+        execute(&engine, &mut data, &mut players, &id, &user, &target);
+        assert!(players.get(&2).unwrap().party.pokemon[0].fainted());
+    }
+
+    #[test]
+    fn berry() {
+        let mut engine = Engine::new::<StepRng>();
+        let id = insert(&mut engine, "tackle", None);
+
+        let mut data = Data::default();
+        let mut players = players();
+        let (user, target) = (TeamIndex(1, 0), TeamIndex(2, 0));
+
+        let berry = hold(
+            &mut engine,
+            &mut players,
+            &target,
+            "sitrusberry",
+            HeldItem {
+                berry: Some(Berry {
+                    threshold: 50,
+                    heal: 25,
+                }),
+                ..held()
+            },
+        );
+
+        let (_, max, ..) = active(&players, &target);
+
+        // above half HP the berry is kept
+        let results = execute(&engine, &mut data, &mut players, &id, &user, &target);
+        assert!(!used(&results, &target, berry, true));
+        assert!(active(&players, &target).2);
+
+        if let Some(pokemon) = players.get_mut(&2).and_then(|p| p.party.active_mut(0)) {
+            pokemon.hp = max / 2 + 1;
+        }
+
+        let results = execute(&engine, &mut data, &mut players, &id, &user, &target);
+        assert!(used(&results, &target, berry, true));
+        assert_eq!(active(&players, &target), (max / 2 + max / 4, max, false));
+    }
+
+    #[test]
+    fn grounded_field() {
+        let engine = Engine::new::<StepRng>();
+
+        let mut data = Data::default();
+        data.field.start(
+            "misty".parse().unwrap(),
+            &FieldCondition {
+                turns: 5,
+                group: None,
+                toggle: false,
+                damage: Vec::new(),
+                slower_first: false,
+                prevent_ailments: true,
+                heal: 10,
+                accuracy: 1.0,
+            },
+        );
+
+        let mut players = players();
+        let (grounded, flying) = (TeamIndex(1, 0), TeamIndex(2, 0));
+
+        for player in players.iter_mut() {
+            let id = *player.id();
+            if let Some(pokemon) = player.party.active_mut(0) {
+                pokemon.hp = 50;
+                if id == 2 {
+                    let mut species = (*pokemon.pokemon).clone();
+                    species.types.secondary = Some(PokemonType::Flying);
+                    pokemon.pokemon = Arc::new(species);
+                }
+            }
+        }
+
+        let mut random = StepRng::new(0, 0);
+        let mut battle = BattleData::default();
+
+        engine
+            .post(&mut data, &mut random, &mut battle, &mut players)
+            .unwrap();
+
+        let hp = |players: &PlayerQuery<u8, ()>, pokemon: &TeamIndex<u8>| {
+            players
+                .get(pokemon.team())
+                .and_then(|p| p.party.active(pokemon.index()))
+                .map(|p| (p.hp, p.max_hp(), p.ailment.is_some()))
+                .unwrap()
+        };
+
+        let (health, max, ..) = hp(&players, &grounded);
+        assert_eq!(health, 50 + max / 10);
+        assert_eq!(hp(&players, &flying).0, 50);
+
+        for pokemon in [&grounded, &flying] {
+            let poison = AilmentLength::Permanent.init(Ailment::Poison, &mut random);
+            run_action(
+                Indexed(pokemon.clone(), ActionResult::Ailment(Some(poison))),
+                &mut random,
+                &mut battle,
+                engine.effects(&data.field),
+                pokemon,
+                &mut Vec::new(),
+                &mut players,
+            );
+        }
+
+        assert!(!hp(&players, &grounded).2);
+        assert!(hp(&players, &flying).2);
+    }
+
+    /// Add a move with its own effects.
+    fn actions(engine: &mut Engine, id: &str, actions: Vec<MoveUse>) -> MoveId {
+        let id = insert(engine, id, None);
+        engine.moves.get_mut(&id).unwrap().usage = MoveExecution::Actions(actions);
+        id
+    }
+
+    #[test]
+    fn recoil() {
+        let mut engine = Engine::new::<StepRng>();
+        let id = actions(
+            &mut engine,
+            "doubleedge",
+            vec![
+                MoveUse::Damage(DamageKind::Constant(40)),
+                MoveUse::Recoil(50),
+            ],
+        );
+
+        let mut data = Data::default();
+        let mut players = players();
+        let (user, target) = (TeamIndex(1, 0), TeamIndex(2, 0));
+
+        let (hp, ..) = active(&players, &user);
+        let (target_hp, ..) = active(&players, &target);
+
+        execute(&engine, &mut data, &mut players, &id, &user, &target);
+        assert_eq!(active(&players, &target).0, target_hp - 40);
+        assert_eq!(active(&players, &user).0, hp - 20);
+
+        // recoil is taken from the damage dealt, not the damage rolled
+        if let Some(pokemon) = players.get_mut(&2).and_then(|p| p.party.active_mut(0)) {
+            pokemon.hp = 10;
+        }
+
+        execute(&engine, &mut data, &mut players, &id, &user, &target);
+        assert!(players.get(&2).unwrap().party.pokemon[0].fainted());
+        assert_eq!(active(&players, &user).0, hp - 25);
+    }
+
+    #[test]
+    fn drain() {
+        let mut engine = Engine::new::<StepRng>();
+        let id = actions(
+            &mut engine,
+            "gigadrain",
+            vec![MoveUse::Drain(DamageKind::Constant(40), 50)],
+        );
+
+        let mut data = Data::default();
+        let mut players = players();
+        let (user, target) = (TeamIndex(1, 0), TeamIndex(2, 0));
+
+        if let Some(pokemon) = players.get_mut(&1).and_then(|p| p.party.active_mut(0)) {
+            pokemon.hp = 50;
+        }
+
+        let (target_hp, ..) = active(&players, &target);
+
+        execute(&engine, &mut data, &mut players, &id, &user, &target);
+        assert_eq!(active(&players, &target).0, target_hp - 40);
+        assert_eq!(active(&players, &user).0, 70);
+    }
+
+    #[test]
+    fn self_ko() {
+        let mut engine = Engine::new::<StepRng>();
+        let id = actions(
+            &mut engine,
+            "explosion",
+            vec![
+                MoveUse::Damage(DamageKind::Constant(40)),
+                MoveUse::User(vec![MoveUse::Faint]),
+            ],
+        );
+
+        let mut data = Data::default();
+        let mut players = players();
+        let (user, target) = (TeamIndex(1, 0), TeamIndex(2, 0));
+
+        let (target_hp, ..) = active(&players, &target);
+
+        let results = execute(&engine, &mut data, &mut players, &id, &user, &target);
+        assert!(hit(&results, &target));
+        assert_eq!(active(&players, &target).0, target_hp - 40);
+        assert!(players.get(&1).unwrap().party.pokemon[0].fainted());
+    }
+
+    #[test]
+    fn user_effects() {
+        let mut engine = Engine::new::<StepRng>();
+        let attack = BattleStatType::Basic(StatType::Attack);
+        let id = actions(
+            &mut engine,
+            "swordsdance",
+            vec![MoveUse::User(vec![
+                MoveUse::Stat(attack, 2),
+                MoveUse::Heal(50),
+            ])],
+        );
+
+        let mut data = Data::default();
+        let mut players = players();
+        let (user, target) = (TeamIndex(1, 0), TeamIndex(2, 0));
+
+        if let Some(pokemon) = players.get_mut(&1).and_then(|p| p.party.active_mut(0)) {
+            pokemon.hp = 10;
+        }
+
+        let (_, max, ..) = active(&players, &user);
+
+        let results = execute(&engine, &mut data, &mut players, &id, &user, &target);
+        assert!(!hit(&results, &target));
+        assert_eq!(active(&players, &user).0, 10 + max / 2);
+
+        let stage = |players: &PlayerQuery<u8, ()>, team: &u8| {
+            players.get(team).unwrap().party.pokemon[0].stages[attack]
+        };
+
+        assert_eq!(stage(&players, &1), 2);
+        assert_eq!(stage(&players, &2), 0);
+    }
+
+    /// Queue a move for every active pokemon and get the order they move in.
+    fn order(
+        engine: &Engine,
+        data: &Data,
+        players: &mut PlayerQuery<u8, ()>,
+    ) -> Vec<TeamIndex<u8>> {
+        let mut queue = BTreeMap::new();
+
+        for player in players.iter_mut() {
+            for active in player.party.active.iter_mut().flatten() {
+                active.queued_move = Some(BattleSelection::Move("tackle".parse().unwrap(), None));
+            }
+            queue_player(
+                engine,
+                data,
+                &mut queue,
+                &player.party.id,
+                &mut player.party.active,
+                &mut player.party.pokemon,
+                &player.party.side,
+                &mut StepRng::new(0, 0),
+            );
+        }
+
+        queue
+            .into_values()
+            .map(|Indexed(pokemon, ..)| pokemon)
+            .collect()
+    }
+
+    #[test]
+    fn tailwind() {
+        let engine = Engine::new::<StepRng>();
+        let data = Data::default();
+        let mut players = players();
+        let (slow, fast) = (TeamIndex(1, 0), TeamIndex(2, 0));
+
+        if let Some(pokemon) = players.get_mut(&2).and_then(|p| p.party.active_mut(0)) {
+            pokemon.stages[BattleStatType::Basic(StatType::Speed)] = 1;
+        }
+
+        assert_eq!(
+            order(&engine, &data, &mut players),
+            vec![fast.clone(), slow.clone()]
+        );
+
+        // doubling speed beats a raised stage
+        let side = &mut players.get_mut(&1).unwrap().party.side;
+        assert!(side.apply(SideEvent::Start(SideCondition::Tailwind)));
+
+        assert_eq!(order(&engine, &data, &mut players), vec![slow, fast]);
+    }
+
+    #[test]
+    fn switch_in() {
+        let ability = "speedboost".parse().unwrap();
+        let speed = BattleStatType::Basic(StatType::Speed);
+
+        let mut engine = Engine::new::<StepRng>();
+        engine
+            .scripting
+            .register_trigger(
+                TriggerSource::Ability(ability),
+                r#"
+                fn on_switch_in(pokemon, other) {
+                    if type_of(other) == "()" {
+                        [Stat(pokemon, "speed", 1)]
+                    } else {
+                        []
+                    }
+                }
+                "#,
+            )
+            .unwrap();
+        let id = insert(&mut engine, "solarbeam", Some(MultiTurn::Charge(None)));
+
+        let mut data = Data::default();
+        let mut players = PlayerQuery::new(vec![player(1, Some(ability)), player(2, None)]);
+        let (pokemon, target) = (TeamIndex(1, 0), TeamIndex(2, 0));
+
+        execute(&engine, &mut data, &mut players, &id, &pokemon, &target);
+        assert!(engine.forced(&data, &pokemon).is_some());
+
+        let results = engine
+            .switch_in(
+                &mut data,
+                &mut StepRng::new(0, 0),
+                &mut BattleData::default(),
+                &pokemon,
+                &mut players,
+            )
+            .unwrap();
+
+        // a pokemon coming in does not carry on the move of the one before it
+        assert_eq!(engine.forced(&data, &pokemon), None);
+        assert!(results.iter().any(|Indexed(p, action)| p == &pokemon
+            && matches!(action, PublicAction::AddStat(stat, 1) if *stat == speed)));
+
+        let active = players
+            .get_mut(&1)
+            .and_then(|p| p.party.active_mut(0))
+            .unwrap();
+        assert_eq!(active.stages[speed], 1);
+
+        active.switch_out();
+        assert_eq!(active.stages[speed], 0);
+    }
+}
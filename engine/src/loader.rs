@@ -0,0 +1,407 @@
+//! Loading engine data from a directory
+//!
+//! The directory is laid out as:
+//!
+//! * `moves/<move id>.ron` - an [EngineMove]
+//! * `items/<item id>.ron` - a [BattleItemExecution]
+//! * `fields/<field id>.ron` - a [FieldCondition]
+//! * `abilities/<ability id>.ron` - an [Ability]
+//! * `held/<item id>.ron` - a [HeldItem]
+//! * `scripts/moves/<move id>.rhai` - script of a move using [MoveExecution::Script]
+//! * `scripts/items/<item id>.rhai` - script of an item using [BattleItemExecution::Script]
+//! * `scripts/abilities/<ability id>.rhai` - hooks of an ability in `abilities`
+//! * `scripts/held/<item id>.rhai` - hooks of a held item in `held`
+
+use core::{
+    fmt::{Display, Formatter, Result as FmtResult},
+    hash::Hash,
+    str::FromStr,
+};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use hashbrown::HashMap;
+
+use battle::{
+    data::FieldId,
+    pokedex::{
+        item::{Item, ItemId},
+        moves::{Move, MoveId},
+        Dex,
+    },
+    pokemon::AbilityId,
+};
+
+use crate::{
+    ability::{Ability, EngineAbilities},
+    field::{EngineFields, FieldCondition},
+    item::{BattleItemExecution, EngineHeldItems, HeldItem},
+    moves::{EngineMove, MoveExecution},
+    scripting::{RhaiScriptError, RhaiScriptingEngine, TriggerSource},
+    DefaultBattleEngine, EngineItems, EngineMoves,
+};
+
+/// Everything read from a data directory.
+#[derive(Debug, Default)]
+pub struct EngineData {
+    pub moves: EngineMoves,
+    pub items: EngineItems,
+    pub fields: EngineFields,
+    pub abilities: EngineAbilities,
+    pub held: EngineHeldItems,
+    /// Sources of move scripts
+    pub move_scripts: HashMap<MoveId, String>,
+    /// Sources of item scripts
+    pub item_scripts: HashMap<ItemId, String>,
+    /// Sources of ability and held item scripts
+    pub triggers: HashMap<TriggerSource, String>,
+}
+
+/// A problem found while loading a data directory.
+#[derive(Debug)]
+pub enum DataError {
+    Io(PathBuf, std::io::Error),
+    Parse(PathBuf, ron::error::SpannedError),
+    /// The file is not named after a valid id.
+    InvalidId(PathBuf),
+    /// The move in the file has a different id than its file name.
+    MismatchedId(PathBuf, MoveId),
+    MoveNotInDex(MoveId),
+    ItemNotInDex(ItemId),
+    /// The move uses [MoveExecution::Script] but has no script file.
+    MissingMoveScript(MoveId),
+    /// The item uses [BattleItemExecution::Script] but has no script file.
+    MissingItemScript(ItemId),
+    /// A script file that no move uses.
+    OrphanMoveScript(PathBuf),
+    /// A script file that no item uses.
+    OrphanItemScript(PathBuf),
+    /// A script file that no ability uses.
+    OrphanAbilityScript(PathBuf),
+    /// A script file that no held item uses.
+    OrphanHeldScript(PathBuf),
+    /// A script that could not be compiled.
+    Script(RhaiScriptError),
+}
+
+impl EngineData {
+    /// Read a data directory and check it against the dexes.
+    /// Every problem found is returned at once.
+    pub fn load(
+        directory: impl AsRef<Path>,
+        movedex: &Dex<Move>,
+        itemdex: &Dex<Item>,
+    ) -> Result<Self, Vec<DataError>> {
+        let directory = directory.as_ref();
+        let mut errors = Vec::new();
+        let mut data = Self::default();
+
+        for (id, path) in files::<MoveId>(&directory.join("moves"), "ron", &mut errors) {
+            if let Some(m) = parse::<EngineMove>(&path, &mut errors) {
+                if m.data.id != id {
+                    errors.push(DataError::MismatchedId(path, m.data.id));
+                    continue;
+                }
+                data.moves.insert(id, m);
+            }
+        }
+
+        for (id, path) in files::<ItemId>(&directory.join("items"), "ron", &mut errors) {
+            if let Some(item) = parse::<BattleItemExecution>(&path, &mut errors) {
+                data.items.insert(id, item);
+            }
+        }
+
+        for (id, path) in files::<FieldId>(&directory.join("fields"), "ron", &mut errors) {
+            if let Some(condition) = parse::<FieldCondition>(&path, &mut errors) {
+                data.fields.insert(id, condition);
+            }
+        }
+
+        for (id, path) in files::<AbilityId>(&directory.join("abilities"), "ron", &mut errors) {
+            if let Some(ability) = parse::<Ability>(&path, &mut errors) {
+                data.abilities.insert(id, ability);
+            }
+        }
+
+        for (id, path) in files::<ItemId>(&directory.join("held"), "ron", &mut errors) {
+            if itemdex.try_get(&id).is_none() {
+                errors.push(DataError::ItemNotInDex(id));
+            }
+            if let Some(item) = parse::<HeldItem>(&path, &mut errors) {
+                data.held.insert(id, item);
+            }
+        }
+
+        let scripts = directory.join("scripts");
+
+        for (id, path) in files::<MoveId>(&scripts.join("moves"), "rhai", &mut errors) {
+            match data.moves.get(&id).map(|m| &m.usage) {
+                Some(MoveExecution::Script) => {
+                    if let Some(script) = read(&path, &mut errors) {
+                        data.move_scripts.insert(id, script);
+                    }
+                }
+                _ => errors.push(DataError::OrphanMoveScript(path)),
+            }
+        }
+
+        for (id, path) in files::<ItemId>(&scripts.join("items"), "rhai", &mut errors) {
+            match data.items.get(&id) {
+                Some(BattleItemExecution::Script) => {
+                    if let Some(script) = read(&path, &mut errors) {
+                        data.item_scripts.insert(id, script);
+                    }
+                }
+                _ => errors.push(DataError::OrphanItemScript(path)),
+            }
+        }
+
+        for (id, path) in files::<AbilityId>(&scripts.join("abilities"), "rhai", &mut errors) {
+            if !data.abilities.contains_key(&id) {
+                errors.push(DataError::OrphanAbilityScript(path));
+                continue;
+            }
+            if let Some(script) = read(&path, &mut errors) {
+                data.triggers.insert(TriggerSource::Ability(id), script);
+            }
+        }
+
+        for (id, path) in files::<ItemId>(&scripts.join("held"), "rhai", &mut errors) {
+            if !data.held.contains_key(&id) {
+                errors.push(DataError::OrphanHeldScript(path));
+                continue;
+            }
+            if let Some(script) = read(&path, &mut errors) {
+                data.triggers.insert(TriggerSource::Item(id), script);
+            }
+        }
+
+        for (id, m) in data.moves.iter() {
+            if movedex.try_get(id).is_none() {
+                errors.push(DataError::MoveNotInDex(*id));
+            }
+            if matches!(m.usage, MoveExecution::Script) && !data.move_scripts.contains_key(id) {
+                errors.push(DataError::MissingMoveScript(*id));
+            }
+        }
+
+        for (id, item) in data.items.iter() {
+            if itemdex.try_get(id).is_none() {
+                errors.push(DataError::ItemNotInDex(*id));
+            }
+            if matches!(item, BattleItemExecution::Script) && !data.item_scripts.contains_key(id) {
+                errors.push(DataError::MissingItemScript(*id));
+            }
+        }
+
+        match errors.is_empty() {
+            true => Ok(data),
+            false => Err(errors),
+        }
+    }
+}
+
+impl<ID: Clone + Hash + Eq + Send + Sync + 'static, T: Send + Sync + 'static>
+    DefaultBattleEngine<ID, T, RhaiScriptingEngine>
+{
+    /// Load a data directory into this engine and compile its scripts.
+    /// See the [module](self) documentation for its layout.
+    pub fn load(
+        &mut self,
+        directory: impl AsRef<Path>,
+        movedex: &Dex<Move>,
+        itemdex: &Dex<Item>,
+    ) -> Result<(), Vec<DataError>> {
+        let data = EngineData::load(directory, movedex, itemdex)?;
+
+        let mut errors = Vec::new();
+
+        if let Err(err) = self.scripting.register_moves(data.move_scripts) {
+            errors.extend(err.into_iter().map(DataError::Script));
+        }
+
+        if let Err(err) = self.scripting.register_items(data.item_scripts) {
+            errors.extend(err.into_iter().map(DataError::Script));
+        }
+
+        for (source, script) in data.triggers {
+            if let Err(err) = self.scripting.register_trigger(source, &script) {
+                errors.push(DataError::Script(err));
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        self.moves.extend(data.moves);
+        self.items.extend(data.items);
+        self.fields.extend(data.fields);
+        self.abilities.extend(data.abilities);
+        self.held.extend(data.held);
+        Ok(())
+    }
+}
+
+/// Files in a directory with an extension, keyed by their name.
+/// A missing directory has no files.
+fn files<K: FromStr>(
+    directory: &Path,
+    extension: &str,
+    errors: &mut Vec<DataError>,
+) -> Vec<(K, PathBuf)> {
+    let mut files = Vec::new();
+
+    if !directory.is_dir() {
+        return files;
+    }
+
+    let entries = match fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(err) => {
+            errors.push(DataError::Io(directory.to_owned(), err));
+            return files;
+        }
+    };
+
+    for entry in entries {
+        let path = match entry {
+            Ok(entry) => entry.path(),
+            Err(err) => {
+                errors.push(DataError::Io(directory.to_owned(), err));
+                continue;
+            }
+        };
+
+        if path.extension().map(|e| e != extension).unwrap_or(true) {
+            continue;
+        }
+
+        match path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .and_then(|s| s.parse().ok())
+        {
+            Some(id) => files.push((id, path)),
+            None => errors.push(DataError::InvalidId(path)),
+        }
+    }
+
+    files
+}
+
+fn read(path: &Path, errors: &mut Vec<DataError>) -> Option<String> {
+    match fs::read_to_string(path) {
+        Ok(data) => Some(data),
+        Err(err) => {
+            errors.push(DataError::Io(path.to_owned(), err));
+            None
+        }
+    }
+}
+
+fn parse<D: serde::de::DeserializeOwned>(path: &Path, errors: &mut Vec<DataError>) -> Option<D> {
+    let data = read(path, errors)?;
+    match ron::from_str(&data) {
+        Ok(data) => Some(data),
+        Err(err) => {
+            errors.push(DataError::Parse(path.to_owned(), err));
+            None
+        }
+    }
+}
+
+impl std::error::Error for DataError {}
+
+impl Display for DataError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::Io(path, err) => write!(f, "Could not read {}: {}", path.display(), err),
+            Self::Parse(path, err) => write!(f, "Could not parse {}: {}", path.display(), err),
+            Self::InvalidId(path) => {
+                write!(f, "{} is not named after a valid id", path.display())
+            }
+            Self::MismatchedId(path, id) => {
+                write!(f, "{} contains move {}", path.display(), &id.0)
+            }
+            Self::MoveNotInDex(id) => write!(f, "Move {} is not in the move dex", &id.0),
+            Self::ItemNotInDex(id) => write!(f, "Item {} is not in the item dex", id.as_str()),
+            Self::MissingMoveScript(id) => write!(f, "Move {} has no script", &id.0),
+            Self::MissingItemScript(id) => write!(f, "Item {} has no script", id.as_str()),
+            Self::OrphanMoveScript(path) => {
+                write!(f, "No scripted move uses {}", path.display())
+            }
+            Self::OrphanItemScript(path) => {
+                write!(f, "No scripted item uses {}", path.display())
+            }
+            Self::OrphanAbilityScript(path) => {
+                write!(f, "No ability uses {}", path.display())
+            }
+            Self::OrphanHeldScript(path) => {
+                write!(f, "No held item uses {}", path.display())
+            }
+            Self::Script(err) => Display::fmt(err, f),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::{fs, path::Path};
+
+    use battle::pokedex::Dex;
+
+    use super::{DataError, EngineData};
+
+    fn write(directory: &Path, file: &str, contents: &str) {
+        let path = directory.join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    #[test]
+    fn errors() {
+        let directory = std::env::temp_dir().join(format!("battle-loader-{}", std::process::id()));
+
+        write(&directory, "moves/this-name-is-far-too-long.ron", "");
+        write(&directory, "moves/tackle.ron", "not ron");
+        write(&directory, "fields/gravity.ron", "(turns: 5)");
+        write(&directory, "scripts/moves/tackle.rhai", "");
+        write(&directory, "scripts/abilities/levitate.rhai", "");
+        write(&directory, "scripts/held/leftovers.rhai", "");
+
+        let errors = EngineData::load(&directory, &Dex::default(), &Dex::default()).unwrap_err();
+
+        fs::remove_dir_all(&directory).unwrap();
+
+        assert_eq!(errors.len(), 5, "{:?}", errors);
+        assert!(errors.iter().any(|e| matches!(e, DataError::InvalidId(..))));
+        assert!(errors.iter().any(|e| matches!(e, DataError::Parse(..))));
+        assert!(errors
+            .iter()
+            .any(|e| matches!(e, DataError::OrphanMoveScript(..))));
+        assert!(errors
+            .iter()
+            .any(|e| matches!(e, DataError::OrphanAbilityScript(..))));
+        assert!(errors
+            .iter()
+            .any(|e| matches!(e, DataError::OrphanHeldScript(..))));
+    }
+
+    #[test]
+    fn empty() {
+        let data = EngineData::load(
+            std::env::temp_dir().join("battle-loader-missing"),
+            &Dex::default(),
+            &Dex::default(),
+        )
+        .unwrap();
+
+        assert!(data.moves.is_empty());
+        assert!(data.triggers.is_empty());
+    }
+}
@@ -3,13 +3,20 @@ use rand::Rng;
 use serde::{Deserialize, Serialize};
 
 use battle::{
+    data::{BattleWeather, FieldId, Weather},
     engine::ActionResult,
-    moves::{BattleMove, DamageKind, Percent},
-    pokedex::ailment::{Ailment, AilmentLength},
+    moves::{BattleMove, DamageKind, DamageModifiers, Percent},
+    pokedex::{
+        ailment::{Ailment, AilmentLength},
+        pokemon::Health,
+        types::Effective,
+    },
     pokemon::{
         stat::{BattleStatType, Stage},
-        BattlePokemon, Indexed,
+        volatile::VolatileStatus,
+        BattlePokemon, Indexed, TeamIndex,
     },
+    side::SideEvent,
 };
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -26,13 +33,142 @@ pub enum MoveExecution {
 #[serde(deny_unknown_fields)]
 pub enum MoveUse {
     Damage(DamageKind),
+    /// Damage the target more than once, stopping if it faints
+    MultiHit(DamageKind, Hits),
     Ailment(Option<(Ailment, AilmentLength)>, Percent),
+    /// Badly poison the target with a chance
+    Toxic(Percent),
     Drain(DamageKind, i8),
     Stat(BattleStatType, Stage),
+    /// Heal the target by a percent of its max HP
+    Heal(Percent),
+    /// Make the target faint
+    Faint,
+    /// The user loses a percent of the damage the move dealt
+    Recoil(Percent),
+    /// Apply the effects to the user instead of the target
+    User(Vec<Self>),
     Flinch,
+    /// Give the target a volatile status with a chance
+    Volatile(VolatileStatus, Percent),
+    /// Start a weather for a number of turns
+    Weather(Weather, u8),
+    /// Start a field condition registered with the engine
+    Field(FieldId),
+    /// Change the side of the target's team
+    Side(SideEvent),
     Chance(Vec<Self>, Percent),
 }
 
+/// How many times a multi-hit move hits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Hits {
+    Fixed(u8),
+    /// 2 to 5 times, with 2 or 3 hits being more likely
+    Random,
+    /// Up to a number of times, the power growing by the first hit's power each hit, like Triple Kick
+    Escalating(u8),
+}
+
+impl Hits {
+    pub const MAX_RANDOM: u8 = 5;
+
+    pub fn max(&self) -> u8 {
+        match self {
+            Self::Fixed(hits) | Self::Escalating(hits) => *hits,
+            Self::Random => Self::MAX_RANDOM,
+        }
+    }
+
+    pub fn roll(&self, random: &mut impl Rng) -> u8 {
+        match self {
+            Self::Random => match random.gen_range(0..20) {
+                0..=6 => 2,
+                7..=13 => 3,
+                14..=16 => 4,
+                _ => 5,
+            },
+            hits => hits.max(),
+        }
+    }
+}
+
+/// HP a target has left while the hits of a move are worked out.
+struct Remaining {
+    substitute: Health,
+    hp: Health,
+}
+
+impl Remaining {
+    /// Replays the damage already done to the target by the move.
+    fn new<ID: PartialEq>(
+        user: &TeamIndex<ID>,
+        target_id: &TeamIndex<ID>,
+        target: &BattlePokemon,
+        results: &[Indexed<ID, ActionResult>],
+    ) -> Self {
+        let mut remaining = Self::untouched(user, target_id, target);
+        remaining.replay(target_id, results);
+        remaining
+    }
+
+    /// The damage the move has actually dealt to the target so far.
+    fn dealt<ID: PartialEq>(
+        user: &TeamIndex<ID>,
+        target_id: &TeamIndex<ID>,
+        target: &BattlePokemon,
+        results: &[Indexed<ID, ActionResult>],
+    ) -> Health {
+        Self::untouched(user, target_id, target).replay(target_id, results)
+    }
+
+    fn untouched<ID: PartialEq>(
+        user: &TeamIndex<ID>,
+        target_id: &TeamIndex<ID>,
+        target: &BattlePokemon,
+    ) -> Self {
+        Self {
+            // a substitute only takes hits from other pokemon
+            substitute: match target_id != user {
+                true => target.volatile.substitute.unwrap_or_default(),
+                false => 0,
+            },
+            hp: target.hp(),
+        }
+    }
+
+    fn replay<ID: PartialEq>(
+        &mut self,
+        target_id: &TeamIndex<ID>,
+        results: &[Indexed<ID, ActionResult>],
+    ) -> Health {
+        results
+            .iter()
+            .flat_map(|Indexed(id, result)| match result {
+                ActionResult::Damage(result) if id == target_id => Some(result.damage),
+                _ => None,
+            })
+            .fold(0, |dealt: Health, damage| {
+                dealt.saturating_add(self.hit(damage))
+            })
+    }
+
+    /// Returns the damage the hit actually dealt.
+    fn hit(&mut self, damage: Health) -> Health {
+        let health = match self.substitute != 0 {
+            true => &mut self.substitute,
+            false => &mut self.hp,
+        };
+        let dealt = damage.min(*health);
+        *health -= dealt;
+        dealt
+    }
+
+    fn fainted(&self) -> bool {
+        self.hp == 0
+    }
+}
+
 impl MoveExecution {
     pub fn size(&self) -> usize {
         match self {
@@ -45,20 +181,22 @@ impl MoveExecution {
 impl MoveUse {
     pub fn size(&self) -> usize {
         match self {
-            Self::Chance(uses, ..) => uses.iter().map(Self::size).sum(),
-            Self::Drain(..) => 2,
+            Self::Chance(uses, ..) | Self::User(uses) => uses.iter().map(Self::size).sum(),
+            Self::Drain(..) | Self::Toxic(..) => 2,
+            Self::MultiHit(_, hits) => hits.max() as usize + 1,
             _ => 1,
         }
     }
 }
 
-pub fn move_usage<ID: Clone, R: Rng>(
+pub fn move_usage<ID: Clone + PartialEq, R: Rng>(
     user: &Indexed<ID, &BattlePokemon>,
     random: &mut R,
     results: &mut Vec<Indexed<ID, ActionResult>>,
     actions: &[MoveUse],
     m: &BattleMove,
     Indexed(target_id, target): Indexed<ID, &BattlePokemon>,
+    modifiers: &DamageModifiers,
 ) {
     for action in actions {
         match action {
@@ -72,9 +210,38 @@ pub fn move_usage<ID: Clone, R: Rng>(
                         m.category,
                         m.pokemon_type,
                         m.crit_rate,
+                        modifiers,
                     )),
                 ));
             }
+            MoveUse::MultiHit(kind, hits) => {
+                let mut remaining = Remaining::new(&user.0, &target_id, target, results);
+                let mut count = 0;
+                for hit in 1..=hits.roll(random) {
+                    let kind = match (hits, kind) {
+                        (Hits::Escalating(..), DamageKind::Power(power)) => {
+                            DamageKind::Power(power.saturating_mul(hit))
+                        }
+                        _ => *kind,
+                    };
+                    let result = user.1.damage_kind(
+                        random,
+                        target,
+                        kind,
+                        m.category,
+                        m.pokemon_type,
+                        m.crit_rate,
+                        modifiers,
+                    );
+                    remaining.hit(result.damage);
+                    count = hit;
+                    results.push(Indexed(target_id.clone(), ActionResult::Damage(result)));
+                    if remaining.fainted() || matches!(result.effective, Effective::Ineffective) {
+                        break;
+                    }
+                }
+                results.push(Indexed(user.0.clone(), ActionResult::Hits(count)));
+            }
             MoveUse::Ailment(effect, chance) => {
                 if random.gen_bool(*chance as f64 / 100.0) {
                     match effect {
@@ -95,6 +262,16 @@ pub fn move_usage<ID: Clone, R: Rng>(
                     }
                 }
             }
+            MoveUse::Toxic(chance) => {
+                if target.ailment.is_none() && random.gen_bool(*chance as f64 / 100.0) {
+                    let poison = AilmentLength::Permanent.init(Ailment::Poison, random);
+                    results.push(Indexed(
+                        target_id.clone(),
+                        ActionResult::Ailment(Some(poison)),
+                    ));
+                    results.push(Indexed(target_id.clone(), ActionResult::Toxic));
+                }
+            }
             MoveUse::Drain(kind, percent) => {
                 let result = user.1.damage_kind(
                     random,
@@ -103,6 +280,7 @@ pub fn move_usage<ID: Clone, R: Rng>(
                     m.category,
                     m.pokemon_type,
                     m.crit_rate,
+                    modifiers,
                 );
 
                 let healing = (result.damage as f32 * *percent as f32 / 100.0) as i16;
@@ -118,13 +296,61 @@ pub fn move_usage<ID: Clone, R: Rng>(
                     ));
                 }
             }
+            MoveUse::Heal(percent) => {
+                let health = (target.max_hp() as u32 * *percent as u32 / 100).max(1);
+                results.push(Indexed(
+                    target_id.clone(),
+                    ActionResult::Heal(health as i16),
+                ));
+            }
+            MoveUse::Faint => results.push(Indexed(
+                target_id.clone(),
+                ActionResult::Heal(-(target.hp() as i16)),
+            )),
+            MoveUse::Recoil(percent) => {
+                let damage = Remaining::dealt(&user.0, &target_id, target, results) as u32;
+                if damage != 0 {
+                    let recoil = (damage * *percent as u32 / 100).max(1);
+                    results.push(Indexed(
+                        user.0.clone(),
+                        ActionResult::Heal(-(recoil as i16)),
+                    ));
+                }
+            }
+            MoveUse::User(actions) => move_usage(
+                user,
+                random,
+                results,
+                actions,
+                m,
+                Indexed(user.0.clone(), user.1),
+                modifiers,
+            ),
             // MoveUseType::Linger(..) => {
             // 	results.insert(target.instance, Some(MoveAction::Todo));
             // }
             MoveUse::Flinch => results.push(Indexed(
                 target_id.clone(),
-                ActionResult::Cancel("flinch".parse().unwrap()),
+                ActionResult::Volatile(VolatileStatus::Flinch, true),
             )),
+            MoveUse::Volatile(status, chance) => {
+                if !target.volatile.has(*status) && random.gen_bool(*chance as f64 / 100.0) {
+                    results.push(Indexed(
+                        target_id.clone(),
+                        ActionResult::Volatile(*status, true),
+                    ));
+                }
+            }
+            MoveUse::Weather(weather, turns) => results.push(Indexed(
+                user.0.clone(),
+                ActionResult::Weather(Some(BattleWeather::new(*weather, Some(*turns)))),
+            )),
+            MoveUse::Field(id) => {
+                results.push(Indexed(user.0.clone(), ActionResult::Field(*id, true)))
+            }
+            MoveUse::Side(event) => {
+                results.push(Indexed(target_id.clone(), ActionResult::Side(*event)))
+            }
             MoveUse::Chance(actions, chance) => {
                 if random.gen_range(0..=100) < *chance {
                     move_usage(
@@ -134,9 +360,54 @@ pub fn move_usage<ID: Clone, R: Rng>(
                         actions,
                         m,
                         Indexed(target_id.clone(), target),
+                        modifiers,
                     );
                 }
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+
+    use rand::rngs::mock::StepRng;
+
+    use super::{Hits, Remaining};
+
+    #[test]
+    fn hits() {
+        let mut random = StepRng::new(0, 0x9E37_79B9_7F4A_7C15);
+
+        assert_eq!(Hits::Fixed(2).roll(&mut random), 2);
+        assert_eq!(Hits::Escalating(3).roll(&mut random), 3);
+        assert_eq!(Hits::Random.max(), Hits::MAX_RANDOM);
+
+        let rolls = (0..100)
+            .map(|_| Hits::Random.roll(&mut random))
+            .collect::<Vec<_>>();
+        assert!(rolls
+            .iter()
+            .all(|hits| (2..=Hits::MAX_RANDOM).contains(hits)));
+        assert!(rolls.contains(&2));
+        assert!(rolls.contains(&Hits::MAX_RANDOM));
+    }
+
+    #[test]
+    fn remaining() {
+        let mut remaining = Remaining {
+            substitute: 10,
+            hp: 20,
+        };
+
+        // a substitute takes the whole hit and does not pass the rest on
+        assert_eq!(remaining.hit(15), 10);
+        assert_eq!(remaining.hp, 20);
+
+        assert_eq!(remaining.hit(15), 15);
+        assert!(!remaining.fainted());
+        assert_eq!(remaining.hit(15), 5);
+        assert!(remaining.fainted());
+        assert_eq!(remaining.hit(15), 0);
+    }
+}
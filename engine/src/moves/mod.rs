@@ -2,10 +2,20 @@ use battle::moves::BattleMove;
 
 mod execution;
 pub use execution::*;
+
+mod turns;
+pub use turns::*;
+
 use serde::{Serialize, Deserialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EngineMove {
     pub data: BattleMove,
     pub usage: MoveExecution,
-}
\ No newline at end of file
+    /// How the move spans more than one turn
+    #[serde(default)]
+    pub turns: Option<MultiTurn>,
+    /// Semi-invulnerable pokemon the move can still hit, like Earthquake hitting pokemon underground
+    #[serde(default)]
+    pub reaches: Vec<SemiInvulnerable>,
+}
@@ -0,0 +1,54 @@
+//! Moves that last more than one turn
+
+use serde::{Deserialize, Serialize};
+
+use battle::{pokedex::moves::MoveId, pokemon::TeamIndex, select::BattleSelection};
+
+/// How a move spans more than one turn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MultiTurn {
+    /// Charges on the first turn and hits on the second, like Solar Beam.
+    /// The user can be semi-invulnerable while it charges, like with Fly.
+    Charge(Option<SemiInvulnerable>),
+    /// The user has to recharge on the next turn, like with Hyper Beam
+    Recharge,
+    /// The user repeats the move for 2 to 3 turns and then becomes confused, like with Outrage
+    Rampage,
+}
+
+/// Where a pokemon is while most moves cannot hit it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SemiInvulnerable {
+    Flying,
+    Underground,
+    Underwater,
+}
+
+/// What a pokemon is doing between the turns of a multi-turn move.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MoveState<ID> {
+    Charging(MoveId, Option<TeamIndex<ID>>, Option<SemiInvulnerable>),
+    Recharging(MoveId),
+    /// Turns left of the rampage after the next one
+    Rampage(MoveId, Option<TeamIndex<ID>>, u8),
+}
+
+impl<ID: Clone> MoveState<ID> {
+    /// The selection the pokemon has to make on the next turn.
+    pub fn selection(&self) -> BattleSelection<ID> {
+        match self {
+            Self::Charging(id, target, ..) | Self::Rampage(id, target, ..) => {
+                BattleSelection::Move(*id, target.clone())
+            }
+            Self::Recharging(id) => BattleSelection::Move(*id, None),
+        }
+    }
+
+    /// Where the pokemon is if it cannot be hit by most moves.
+    pub fn invulnerable(&self) -> Option<SemiInvulnerable> {
+        match self {
+            Self::Charging(.., invulnerable) => *invulnerable,
+            _ => None,
+        }
+    }
+}
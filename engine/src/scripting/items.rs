@@ -0,0 +1,30 @@
+use rhai::{Array, Dynamic};
+
+use battle::pokedex::item::ItemId;
+
+use super::ScriptPokemon;
+
+#[derive(Debug, Clone, Copy)]
+pub struct ScriptItem(pub ItemId);
+
+impl ScriptItem {
+    pub fn get_id(&mut self) -> String {
+        self.0.as_str().to_owned()
+    }
+}
+
+/// The player using an item.
+#[derive(Debug, Clone)]
+pub struct ScriptPlayer {
+    active: Vec<ScriptPokemon>,
+}
+
+impl ScriptPlayer {
+    pub fn new(active: Vec<ScriptPokemon>) -> Self {
+        Self { active }
+    }
+
+    pub fn get_active(&mut self) -> Array {
+        self.active.iter().cloned().map(Dynamic::from).collect()
+    }
+}
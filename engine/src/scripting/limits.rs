@@ -0,0 +1,73 @@
+use core::{cell::Cell, time::Duration};
+use std::time::Instant;
+
+use rhai::{Dynamic, Engine};
+
+/// Limits on a single `execute_move` or `execute_item` call.
+#[derive(Debug, Clone, Copy)]
+pub struct ScriptLimits {
+    /// Operations a script may run, 0 for no limit
+    pub operations: u64,
+    /// Depth of nested function calls, 0 for the default.
+    /// It cannot be unlimited since a script recursing forever would overflow the stack.
+    pub call_depth: usize,
+    /// Expression depth of global and function level code, 0 for no limit
+    pub expr_depths: (usize, usize),
+    /// Length of a string, 0 for no limit
+    pub string_size: usize,
+    /// Length of an array, 0 for no limit
+    pub array_size: usize,
+    /// Wall clock time a script may run for
+    pub time: Option<Duration>,
+}
+
+impl ScriptLimits {
+    pub const DEFAULT_CALL_DEPTH: usize = 32;
+}
+
+impl Default for ScriptLimits {
+    fn default() -> Self {
+        Self {
+            operations: 100_000,
+            call_depth: Self::DEFAULT_CALL_DEPTH,
+            expr_depths: (64, 32),
+            string_size: 4096,
+            array_size: 1024,
+            time: Some(Duration::from_millis(100)),
+        }
+    }
+}
+
+std::thread_local! {
+    /// When the script running on this thread has to stop.
+    static DEADLINE: Cell<Option<Instant>> = Cell::new(None);
+}
+
+/// Token the engine is terminated with once a script runs out of time.
+pub(crate) const TIMEOUT: &str = "timeout";
+
+impl ScriptLimits {
+    pub(crate) fn apply(&self, engine: &mut Engine) {
+        engine
+            .set_max_operations(self.operations)
+            // rhai does not allow any calls with a depth of 0
+            .set_max_call_levels(match self.call_depth {
+                0 => Self::DEFAULT_CALL_DEPTH,
+                depth => depth,
+            })
+            .set_max_expr_depths(self.expr_depths.0, self.expr_depths.1)
+            .set_max_string_size(self.string_size)
+            .set_max_array_size(self.array_size)
+            .on_progress(|_| {
+                DEADLINE
+                    .with(Cell::get)
+                    .filter(|deadline| Instant::now() > *deadline)
+                    .map(|_| Dynamic::from(TIMEOUT))
+            });
+    }
+
+    /// Start the clock for a script about to run on this thread.
+    pub(crate) fn start(&self) {
+        DEADLINE.with(|d| d.set(self.time.map(|time| Instant::now() + time)));
+    }
+}
@@ -1,5 +1,5 @@
 use core::{fmt::Debug, hash::Hash};
-use std::error::Error;
+use std::{error::Error, sync::Arc};
 
 // use std::error::Error;
 
@@ -7,26 +7,54 @@ use hashbrown::HashMap;
 use rand::Rng;
 use rhai::{
     packages::{BasicArrayPackage, Package},
-    Engine, EvalAltResult, ParseError, Scope,
+    Array, Dynamic, Engine, EvalAltResult, FuncArgs, ParseError, Scope, AST, FLOAT,
 };
 
 use battle::{
-    data::BattleData,
+    data::{BattleData, Weather},
     engine::{ActionResult, PlayerQuery},
     moves::{BattleMove, MoveCategory},
-    pokedex::{item::ItemId, moves::MoveId},
-    pokemon::{Indexed, TeamIndex},
+    pokedex::{
+        ailment::Ailment,
+        item::ItemId,
+        moves::MoveId,
+        pokemon::stat::StatType,
+        types::{Effective, PokemonType},
+    },
+    pokemon::{
+        stat::{BattleStatType, StatStages},
+        Indexed, TeamIndex,
+    },
     select::PublicAction,
 };
 
-type Scripts<ID> = HashMap<ID, String>;
+use crate::effects::Effects;
+
+/// Compiled scripts, shared between every battle using the engine.
+type Scripts<ID> = HashMap<ID, Arc<AST>>;
 
 pub type MoveScripts = Scripts<MoveId>;
 pub type ItemScripts = Scripts<ItemId>;
+pub type TriggerScripts = Scripts<TriggerSource>;
 
 mod moves;
 pub use moves::*;
 
+mod items;
+pub use items::*;
+
+mod limits;
+pub use limits::ScriptLimits;
+
+mod triggers;
+pub use triggers::*;
+
+mod native;
+pub use native::*;
+
+mod teams;
+pub use teams::ScriptTeams;
+
 pub trait ScriptingEngine<ID, T> {
     /// Current battle data
     type Data: Default;
@@ -38,6 +66,7 @@ pub trait ScriptingEngine<ID, T> {
         data: &mut Self::Data,
         random: &mut (impl Rng + Clone + Send + Sync + 'static),
         battle: &mut BattleData,
+        effects: Effects,
         m: &BattleMove,
         user: &TeamIndex<ID>,
         targets: Vec<TeamIndex<ID>>,
@@ -49,11 +78,32 @@ pub trait ScriptingEngine<ID, T> {
         data: &mut Self::Data,
         random: &mut (impl Rng + Clone + Send + Sync + 'static),
         battle: &mut BattleData,
+        effects: Effects,
         item: &ItemId,
         user: &ID,
         target: TeamIndex<ID>,
         players: &mut PlayerQuery<ID, T>,
     ) -> Result<Vec<Indexed<ID, PublicAction>>, Self::ExecutionError>;
+
+    /// Run the passive effect of an ability or item for an event, if it has one.
+    /// `party` is the index of `pokemon` in its party,
+    /// and `other` is the other pokemon involved in the event.
+    fn execute_trigger(
+        &self,
+        data: &mut Self::Data,
+        random: &mut (impl Rng + Clone + Send + Sync + 'static),
+        battle: &mut BattleData,
+        effects: Effects,
+        trigger: Trigger,
+        source: &TriggerSource,
+        pokemon: &TeamIndex<ID>,
+        party: usize,
+        other: Option<&TeamIndex<ID>>,
+        players: &mut PlayerQuery<ID, T>,
+    ) -> Result<Vec<Indexed<ID, PublicAction>>, Self::ExecutionError> {
+        let _ = (data, random, battle, effects, trigger, source, pokemon, party, other, players);
+        Ok(Vec::new())
+    }
 }
 
 pub struct RhaiScriptingEngine {
@@ -61,11 +111,13 @@ pub struct RhaiScriptingEngine {
     pub scope: Scope<'static>,
     pub moves: MoveScripts,
     pub items: ItemScripts,
+    /// Scripts of abilities and held items
+    pub triggers: TriggerScripts,
+    limits: ScriptLimits,
 }
 
 impl RhaiScriptingEngine {
-    pub fn new<ID: Clone + Send + Sync + 'static, R: Rng + Clone + Send + Sync + 'static>() -> Self
-    {
+    pub fn new<R: Rng + Clone + Send + Sync + 'static>() -> Self {
         let mut engine = Engine::new();
 
         engine
@@ -81,38 +133,194 @@ impl RhaiScriptingEngine {
             .register_fn("ailment", ScriptAilmentEffect::ailment)
             .register_fn("init", ScriptAilmentEffect::init::<R>)
             .register_fn("clear_ailment", LiveScriptAilment::clear_ailment)
-            .register_type_with_name::<ScriptPokemon<ID>>("Pokemon")
-            .register_iterator::<Vec<ScriptPokemon<ID>>>()
-            .register_fn("throw_move", ScriptPokemon::<ID>::throw_move::<R>)
-            // .register_fn("ailment_affects", ScriptPokemon::<ID>::ailment_affects)
-            .register_fn("damage", ScriptPokemon::<ID>::get_damage::<R>)
-            .register_get("hp", ScriptPokemon::<ID>::hp)
-            .register_iterator::<Vec<ScriptPokemon<ID>>>()
+            .register_type_with_name::<ScriptPokemon>("Pokemon")
+            .register_iterator::<Vec<ScriptPokemon>>()
+            .register_fn("throw_move", ScriptPokemon::throw_move::<R>)
+            // .register_fn("ailment_affects", ScriptPokemon::ailment_affects)
+            .register_fn("damage", ScriptPokemon::get_damage::<R>)
+            .register_get("hp", ScriptPokemon::hp)
+            .register_get("max_hp", ScriptPokemon::max_hp)
+            .register_get("percent_hp", ScriptPokemon::percent_hp)
+            .register_get("level", ScriptPokemon::level)
+            .register_get("types", ScriptPokemon::types)
+            .register_get("ailment", ScriptPokemon::ailment)
+            .register_get("ability", ScriptPokemon::ability)
+            .register_get("has_ailment", ScriptPokemon::has_ailment)
+            .register_get("item", ScriptPokemon::item)
+            .register_get("moves", ScriptPokemon::moves)
+            .register_get("fainted", ScriptPokemon::fainted)
+            .register_get("weather", ScriptPokemon::weather)
+            .register_get("stages", ScriptPokemon::stages)
+            .register_fn("stat", ScriptPokemon::stat)
+            .register_fn("stage", ScriptPokemon::stage)
+            .register_fn("is", ScriptPokemon::is)
+            .register_type_with_name::<StatStages>("Stages")
+            .register_type_with_name::<BattleStatType>("Stat")
+            .register_type_with_name::<Ailment>("AilmentType")
+            .register_fn("==", |a: Ailment, b: Ailment| a == b)
+            .register_fn("==", |a: PokemonType, b: PokemonType| a == b)
+            .register_type_with_name::<Weather>("WeatherType")
+            .register_fn("==", |a: Weather, b: Weather| a == b)
+            .register_type_with_name::<Effective>("Effective")
+            .register_get("multiplier", |e: &mut Effective| e.multiplier() as FLOAT)
             .register_type_with_name::<ScriptMove>("Move")
             .register_get("category", ScriptMove::get_category)
             .register_get("type", ScriptMove::get_type)
             .register_get("crit_rate", ScriptMove::get_crit_rate)
+            .register_fn("effective", ScriptMove::effective)
             .register_type_with_name::<MoveCategory>("Category")
             .register_type_with_name::<PokemonType>("Type")
             .register_type::<ActionResult>()
-            .register_type_with_name::<ScriptActionResult<ID>>("Result")
-            .register_fn("Miss", ScriptActionResult::<ID>::miss)
-            .register_fn("Damage", ScriptActionResult::<ID>::damage)
-            .register_fn("Ailment", ScriptActionResult::<ID>::ailment)
-            .register_fn("Drain", ScriptActionResult::<ID>::heal);
+            .register_type_with_name::<ScriptActionResult>("Result")
+            .register_fn("Miss", ScriptActionResult::miss)
+            .register_fn("Damage", ScriptActionResult::damage)
+            .register_fn("Ailment", ScriptActionResult::ailment)
+            .register_fn("Toxic", ScriptActionResult::toxic)
+            .register_fn("Drain", ScriptActionResult::heal)
+            .register_fn("Heal", ScriptActionResult::heal)
+            .register_fn("Stat", ScriptActionResult::stat)
+            .register_fn("Stat", ScriptActionResult::stat_named)
+            .register_fn("Cancel", ScriptActionResult::cancel)
+            .register_fn("Flinch", ScriptActionResult::flinch)
+            .register_fn("Volatile", ScriptActionResult::volatile)
+            .register_fn("EndVolatile", ScriptActionResult::end_volatile)
+            .register_fn("Reveal", ScriptActionResult::reveal)
+            .register_fn("Remove", ScriptActionResult::remove)
+            .register_fn("Weather", ScriptActionResult::weather)
+            .register_fn("ClearWeather", ScriptActionResult::clear_weather)
+            .register_fn("Field", ScriptActionResult::field)
+            .register_fn("EndField", ScriptActionResult::end_field)
+            .register_fn("Side", ScriptActionResult::side)
+            .register_fn("EndSide", ScriptActionResult::end_side)
+            .register_fn("Hazard", ScriptActionResult::hazard)
+            .register_fn("ClearHazard", ScriptActionResult::clear_hazard)
+            .register_fn("Fail", ScriptActionResult::fail)
+            .register_type_with_name::<ScriptItem>("Item")
+            .register_get("id", ScriptItem::get_id)
+            .register_type_with_name::<ScriptPlayer>("Player")
+            .register_get("active", ScriptPlayer::get_active);
 
-        engine.set_max_expr_depths(0, 0);
+        let limits = ScriptLimits::default();
+        limits.apply(&mut engine);
         // engine.set_optimization_level(rhai::OptimizationLevel::Full);
 
         let mut scope = Scope::new();
 
         scope.push_constant("CLEAR", LiveScriptAilment::clear_ailment());
 
+        for (name, stat) in [
+            ("ATTACK", BattleStatType::Basic(StatType::Attack)),
+            ("DEFENSE", BattleStatType::Basic(StatType::Defense)),
+            ("SP_ATTACK", BattleStatType::Basic(StatType::SpAttack)),
+            ("SP_DEFENSE", BattleStatType::Basic(StatType::SpDefense)),
+            ("SPEED", BattleStatType::Basic(StatType::Speed)),
+            ("ACCURACY", BattleStatType::Accuracy),
+            ("EVASION", BattleStatType::Evasion),
+        ] {
+            scope.push_constant(name, stat);
+        }
+
+        for (name, ailment) in [
+            ("PARALYSIS", Ailment::Paralysis),
+            ("SLEEP", Ailment::Sleep),
+            ("FREEZE", Ailment::Freeze),
+            ("BURN", Ailment::Burn),
+            ("POISON", Ailment::Poison),
+        ] {
+            scope.push_constant(name, ailment);
+        }
+
+        for (name, weather) in [
+            ("RAIN", Weather::Rain),
+            ("SUN", Weather::Sun),
+            ("SANDSTORM", Weather::Sandstorm),
+            ("HAIL", Weather::Hail),
+        ] {
+            scope.push_constant(name, weather);
+        }
+
         Self {
             items: Default::default(),
             moves: Default::default(),
+            triggers: Default::default(),
             engine,
             scope,
+            limits,
+        }
+    }
+
+    pub fn limits(&self) -> &ScriptLimits {
+        &self.limits
+    }
+
+    /// Change the limits scripts run with.
+    pub fn set_limits(&mut self, limits: ScriptLimits) {
+        limits.apply(&mut self.engine);
+        self.limits = limits;
+    }
+
+    /// Compile a move script and register it.
+    pub fn register_move(&mut self, id: MoveId, source: &str) -> Result<(), RhaiScriptError> {
+        let ast = self
+            .engine
+            .compile_with_scope(&self.scope, source)
+            .map_err(|err| RhaiScriptError::Parse(id, err))?;
+        self.moves.insert(id, Arc::new(ast));
+        Ok(())
+    }
+
+    /// Compile an item script and register it.
+    pub fn register_item(&mut self, id: ItemId, source: &str) -> Result<(), RhaiScriptError> {
+        let ast = self
+            .engine
+            .compile_with_scope(&self.scope, source)
+            .map_err(|err| RhaiScriptError::ItemParse(id, err))?;
+        self.items.insert(id, Arc::new(ast));
+        Ok(())
+    }
+
+    /// Compile the script of an ability or held item and register it.
+    /// Its hooks, such as `on_switch_in`, take the pokemon and the other pokemon involved or `()`.
+    pub fn register_trigger(
+        &mut self,
+        source: TriggerSource,
+        script: &str,
+    ) -> Result<(), RhaiScriptError> {
+        let ast = self
+            .engine
+            .compile_with_scope(&self.scope, script)
+            .map_err(|err| RhaiScriptError::TriggerParse(source, err))?;
+        self.triggers.insert(source, Arc::new(ast));
+        Ok(())
+    }
+
+    /// Compile and register many move scripts, returning every script that could not be parsed.
+    pub fn register_moves<S: AsRef<str>>(
+        &mut self,
+        scripts: impl IntoIterator<Item = (MoveId, S)>,
+    ) -> Result<(), Vec<RhaiScriptError>> {
+        let errors = scripts
+            .into_iter()
+            .flat_map(|(id, source)| self.register_move(id, source.as_ref()).err())
+            .collect::<Vec<_>>();
+        match errors.is_empty() {
+            true => Ok(()),
+            false => Err(errors),
+        }
+    }
+
+    /// Compile and register many item scripts, returning every script that could not be parsed.
+    pub fn register_items<S: AsRef<str>>(
+        &mut self,
+        scripts: impl IntoIterator<Item = (ItemId, S)>,
+    ) -> Result<(), Vec<RhaiScriptError>> {
+        let errors = scripts
+            .into_iter()
+            .flat_map(|(id, source)| self.register_item(id, source.as_ref()).err())
+            .collect::<Vec<_>>();
+        match errors.is_empty() {
+            true => Ok(()),
+            false => Err(errors),
         }
     }
 }
@@ -126,84 +334,234 @@ impl<ID: Eq + Hash + Clone + Send + Sync + 'static, T> ScriptingEngine<ID, T>
 
     fn execute_move(
         &self,
-        data: &mut Self::Data,
+        _data: &mut Self::Data,
         random: &mut (impl Rng + Clone + Send + Sync + 'static),
         battle: &mut BattleData,
+        effects: Effects,
         m: &BattleMove,
         user: &TeamIndex<ID>,
         targets: Vec<TeamIndex<ID>>,
         players: &mut PlayerQuery<ID, T>,
     ) -> Result<Vec<Indexed<ID, PublicAction>>, Self::ExecutionError> {
         match self.moves.get(&m.id) {
-            Some(script) => {
-                use rhai::*;
+            Some(ast) => {
+                let teams = ScriptTeams::of(players);
 
-                let mut scope = self.scope.clone();
+                let targets = targets
+                    .iter()
+                    .flat_map(|target| script_pokemon(effects, battle, players, &teams, target))
+                    .collect::<Vec<ScriptPokemon>>();
 
-                scope.push("random", ScriptRandom::new(random));
+                let pokemon = match script_pokemon(effects, battle, players, &teams, user) {
+                    Some(pokemon) => pokemon,
+                    None => return Ok(Vec::new()),
+                };
 
-                let ast = self
-                    .engine
-                    .compile_with_scope(&mut scope, script)
-                    .map_err(|err| RhaiScriptError::Parse(m.id, err))?;
+                self.run(
+                    random,
+                    ast,
+                    "use_move",
+                    (ScriptMove::new(m), pokemon, targets),
+                    &teams,
+                    user,
+                    battle,
+                    effects,
+                    players,
+                )
+            }
+            None => Err(RhaiScriptError::Missing(m.id)),
+        }
+    }
 
-                let mut iter = players.iter_mut();
+    fn execute_item(
+        &self,
+        _data: &mut Self::Data,
+        random: &mut (impl Rng + Clone + Send + Sync + 'static),
+        battle: &mut BattleData,
+        effects: Effects,
+        item: &ItemId,
+        user: &ID,
+        target: TeamIndex<ID>,
+        players: &mut PlayerQuery<ID, T>,
+    ) -> Result<Vec<Indexed<ID, PublicAction>>, Self::ExecutionError> {
+        match self.items.get(item) {
+            Some(ast) => {
+                let teams = ScriptTeams::of(players);
 
-                let targets = targets
-                    .into_iter()
-                    .flat_map(|id| {
-                        iter.find(|p| p.id() == id.team())
-                            .and_then(|p| p.party.active_mut(id.index()))
-                            .map(|r| ScriptPokemon::new(Indexed(id, r)))
-                    })
-                    .collect::<Vec<ScriptPokemon<ID>>>();
-
-                let p = players.get_mut(user.team()).unwrap().party.active_mut(user.index()).unwrap();
-
-                let result: Array = self.engine.call_fn(
-                    &mut scope,
-                    &ast,
-                    "use_move",
-                    (ScriptMove::new(m), ScriptPokemon::<ID>::new(Indexed(user.clone(), p)), targets),
-                )?;
+                let target = match script_pokemon(effects, battle, players, &teams, &target) {
+                    Some(pokemon) => pokemon,
+                    None => return Ok(Vec::new()),
+                };
 
-                let result = result
-                    .into_iter()
-                    .flat_map(Dynamic::try_cast::<ScriptActionResult<ID>>)
-                    .map(|r| r.0)
-                    .collect::<Vec<Indexed<ID, ActionResult>>>();
+                let (user_id, user_player) = match players.get(user) {
+                    Some(player) => {
+                        let party = &player.party;
+                        let user_id = TeamIndex(
+                            user.clone(),
+                            party.active.iter().position(Option::is_some).unwrap_or_default(),
+                        );
+                        let active = party
+                            .active_iter()
+                            .flat_map(|(i, p)| {
+                                teams
+                                    .slot(&TeamIndex(user.clone(), i))
+                                    .map(|slot| {
+                                        ScriptPokemon::new(slot, p, effects.modifiers(battle, &party.side, p))
+                                    })
+                            })
+                            .collect();
+                        (user_id, ScriptPlayer::new(active))
+                    }
+                    None => return Ok(Vec::new()),
+                };
 
-                let mut actions = Vec::new();
+                let actions = self.run(
+                    random,
+                    ast,
+                    "use_item",
+                    (ScriptItem(*item), user_player, target),
+                    &teams,
+                    &user_id,
+                    battle,
+                    effects,
+                    players,
+                )?;
 
-                for action in result {
-                    crate::run_action(action, battle, user, &mut actions, players);
+                if let Some(player) = players.get_mut(user) {
+                    player.bag.try_take(item, 1);
                 }
 
                 Ok(actions)
             }
-            None => Err(RhaiScriptError::Missing(m.id)),
+            None => Err(RhaiScriptError::MissingItem(*item)),
         }
     }
 
-    fn execute_item(
+    fn execute_trigger(
         &self,
         _data: &mut Self::Data,
-        _random: &mut (impl Rng + Clone + Send + Sync + 'static),
-        _battle: &mut BattleData,
-        _item: &ItemId,
-        _user: &ID,
-        _target: TeamIndex<ID>,
-        _players: &mut PlayerQuery<ID, T>,
+        random: &mut (impl Rng + Clone + Send + Sync + 'static),
+        battle: &mut BattleData,
+        effects: Effects,
+        trigger: Trigger,
+        source: &TriggerSource,
+        pokemon: &TeamIndex<ID>,
+        party: usize,
+        other: Option<&TeamIndex<ID>>,
+        players: &mut PlayerQuery<ID, T>,
     ) -> Result<Vec<Indexed<ID, PublicAction>>, Self::ExecutionError> {
-        Err(RhaiScriptError::Unimplemented)
+        let function = trigger.function();
+
+        let ast = match self.triggers.get(source) {
+            Some(ast) if ast.iter_functions().any(|f| f.name == function) => ast,
+            _ => return Ok(Vec::new()),
+        };
+
+        let teams = ScriptTeams::of(players);
+
+        let user = match players
+            .get(pokemon.team())
+            .and_then(|p| p.party.pokemon.get(party).map(|pokemon| (pokemon, &p.party.side)))
+            .zip(teams.slot(pokemon))
+        {
+            Some(((p, side), slot)) => ScriptPokemon::new(slot, p, effects.modifiers(battle, side, p)),
+            None => return Ok(Vec::new()),
+        };
+
+        let other = other
+            .and_then(|other| script_pokemon(effects, battle, players, &teams, other))
+            .map(Dynamic::from)
+            .unwrap_or(Dynamic::UNIT);
+
+        self.run(
+            random,
+            ast,
+            function,
+            (user, other),
+            &teams,
+            pokemon,
+            battle,
+            effects,
+            players,
+        )
     }
 }
 
+impl RhaiScriptingEngine {
+    /// Call a script function and apply the results it returns.
+    fn run<ID: PartialEq + Clone + Send + Sync + 'static, T, R: Rng + Clone + Send + Sync + 'static>(
+        &self,
+        random: &mut R,
+        ast: &AST,
+        function: &str,
+        args: impl FuncArgs,
+        teams: &ScriptTeams<ID>,
+        user: &TeamIndex<ID>,
+        battle: &mut BattleData,
+        effects: Effects,
+        players: &mut PlayerQuery<ID, T>,
+    ) -> Result<Vec<Indexed<ID, PublicAction>>, RhaiScriptError> {
+        let shared = ScriptRandom::new(random);
+
+        let mut scope = self.scope.clone();
+
+        scope.push("random", shared.clone());
+
+        self.limits.start();
+
+        let result = self.engine.call_fn::<Dynamic>(&mut scope, ast, function, args);
+
+        shared.finish(random);
+
+        let result = result?
+            .try_cast::<Array>()
+            .unwrap_or_default()
+            .into_iter()
+            .flat_map(Dynamic::try_cast::<ScriptActionResult>)
+            .flat_map(|r| teams.result(r))
+            .collect::<Vec<Indexed<ID, ActionResult>>>();
+
+        let mut actions = Vec::new();
+
+        for action in result {
+            if crate::on_field(players, &action.0) {
+                crate::run_action(action, random, battle, effects, user, &mut actions, players);
+            }
+        }
+
+        Ok(actions)
+    }
+}
+
+/// Snapshot of an active pokemon for a script.
+fn script_pokemon<ID: PartialEq + Clone, T>(
+    effects: Effects,
+    battle: &BattleData,
+    players: &PlayerQuery<ID, T>,
+    teams: &ScriptTeams<ID>,
+    position: &TeamIndex<ID>,
+) -> Option<ScriptPokemon> {
+    players
+        .get(position.team())
+        .and_then(|p| {
+            p.party
+                .active(position.index())
+                .map(|pokemon| (pokemon, &p.party.side))
+        })
+        .zip(teams.slot(position))
+        .map(|((p, side), slot)| ScriptPokemon::new(slot, p, effects.modifiers(battle, side, p)))
+}
+
 #[derive(Debug)]
 pub enum RhaiScriptError {
     Parse(MoveId, ParseError),
+    ItemParse(ItemId, ParseError),
+    TriggerParse(TriggerSource, ParseError),
     Evaluate(Box<EvalAltResult>),
+    /// The script went over one of its [ScriptLimits].
+    Limit(Box<EvalAltResult>),
     Missing(MoveId),
+    MissingItem(ItemId),
     Unimplemented,
 }
 
@@ -211,7 +569,13 @@ impl std::error::Error for RhaiScriptError {}
 
 impl From<Box<EvalAltResult>> for RhaiScriptError {
     fn from(r: Box<EvalAltResult>) -> Self {
-        Self::Evaluate(r)
+        match *r {
+            EvalAltResult::ErrorTooManyOperations(..)
+            | EvalAltResult::ErrorStackOverflow(..)
+            | EvalAltResult::ErrorDataTooLarge(..)
+            | EvalAltResult::ErrorTerminated(..) => Self::Limit(r),
+            _ => Self::Evaluate(r),
+        }
     }
 }
 
@@ -223,9 +587,528 @@ impl core::fmt::Display for RhaiScriptError {
                 "Cannot parse move script for {} with error {}",
                 &id.0, err
             ),
+            Self::ItemParse(id, err) => write!(
+                f,
+                "Cannot parse item script for {} with error {}",
+                id.as_str(),
+                err
+            ),
+            Self::TriggerParse(source, err) => {
+                write!(f, "Cannot parse script for {} with error {}", source, err)
+            }
             Self::Evaluate(err) => core::fmt::Display::fmt(err, f),
+            Self::Limit(err) => write!(f, "Script exceeded its limits: {}", err),
             Self::Missing(id) => write!(f, "Could not find move script with id {}", &id.0),
+            Self::MissingItem(id) => {
+                write!(f, "Could not find item script with id {}", id.as_str())
+            }
             Self::Unimplemented => write!(f, "Unimplemented feature!"),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+
+    use std::sync::Arc;
+
+    use rand::rngs::mock::StepRng;
+    use rhai::{Array, Dynamic, EvalAltResult, FLOAT, INT};
+
+    use battle::{
+        data::BattleData,
+        engine::{ActionResult, PlayerQuery},
+        moves::{BattleMove, MoveCategory, MoveTarget},
+        pokedex::{
+            ailment::{Ailment, AilmentLength},
+            item::ItemId,
+            moves::{set::OwnedMoveSet, MoveId},
+            pokemon::{
+                data::{Breeding, Gender, GrowthRate, Training},
+                owned::OwnedPokemon,
+                stat::{StatSet, StatType},
+                Health, Nature, Pokemon, PokemonId,
+            },
+            types::{PokemonType, PokemonTypes},
+        },
+        pokemon::{
+            stat::BattleStatType, volatile::VolatileStatus, BattlePokemon, Indexed, TeamIndex,
+        },
+        select::PublicAction,
+        side::{Hazard, SideCondition, SideEvent},
+    };
+
+    use crate::{effects::Effects, field::Field, tests::players};
+
+    use super::{
+        RhaiScriptError, RhaiScriptingEngine, ScriptActionResult, ScriptLimits, ScriptMove,
+        ScriptPokemon, ScriptTeams, ScriptingEngine,
+    };
+
+    const SCRIPT: &str = r#"
+        fn use_move(user, targets) {
+            let results = [Heal(user, 10)];
+            for target in targets {
+                results.push(Miss(target));
+            }
+            results
+        }
+    "#;
+
+    fn pokemon() -> BattlePokemon {
+        let pokemon = Arc::new(Pokemon {
+            id: PokemonId(1),
+            name: "Bulbasaur".to_owned(),
+            types: PokemonTypes {
+                primary: PokemonType::Grass,
+                secondary: Some(PokemonType::Poison),
+            },
+            moves: vec![],
+            base: StatSet::uniform(50),
+            species: "Seed".to_owned(),
+            evolution: None,
+            height: 7,
+            weight: 69,
+            training: Training {
+                base_exp: 64,
+                growth: GrowthRate::MediumSlow,
+            },
+            breeding: Breeding { gender: Some(1) },
+        });
+
+        let mut pokemon = OwnedPokemon {
+            pokemon,
+            level: 5,
+            gender: Gender::Male,
+            nature: Nature::Hardy,
+            hp: 0,
+            ivs: StatSet::uniform(0),
+            evs: StatSet::uniform(0),
+            friendship: Pokemon::default_friendship(),
+            ailment: None,
+            nickname: None,
+            moves: OwnedMoveSet::default(),
+            item: None,
+            experience: 0,
+        };
+
+        pokemon.heal_hp(None);
+
+        pokemon.into()
+    }
+
+    /// Run a script with pokemon from two teams and check its results come back to the right ones.
+    fn results_reach_teams<ID: PartialEq + Clone + core::fmt::Debug>(user: ID, target: ID) {
+        let engine = RhaiScriptingEngine::new::<StepRng>();
+        let ast = engine
+            .engine
+            .compile_with_scope(&engine.scope, SCRIPT)
+            .unwrap();
+
+        let teams = ScriptTeams::new([user.clone(), target.clone()]);
+        let pokemon = pokemon();
+
+        let user = TeamIndex(user, 0);
+        let target = TeamIndex(target, 1);
+
+        let args = (
+            ScriptPokemon::new(teams.slot(&user).unwrap(), &pokemon, Default::default()),
+            vec![Dynamic::from(ScriptPokemon::new(
+                teams.slot(&target).unwrap(),
+                &pokemon,
+                Default::default(),
+            ))],
+        );
+
+        let results: Array = engine
+            .engine
+            .call_fn(&mut engine.scope.clone(), &ast, "use_move", args)
+            .unwrap();
+
+        let results = results
+            .into_iter()
+            .flat_map(Dynamic::try_cast::<ScriptActionResult>)
+            .flat_map(|r| teams.result(r))
+            .collect::<Vec<_>>();
+
+        assert_eq!(results.len(), 2);
+
+        let Indexed(position, action) = &results[0];
+        assert_eq!(position, &user);
+        assert!(matches!(action, ActionResult::Heal(10)));
+
+        let Indexed(position, action) = &results[1];
+        assert_eq!(position, &target);
+        assert!(matches!(action, ActionResult::Miss));
+    }
+
+    #[test]
+    fn u8_ids() {
+        results_reach_teams(1u8, 2u8);
+    }
+
+    #[test]
+    fn i32_ids() {
+        results_reach_teams(-1i32, 7i32);
+    }
+
+    #[test]
+    fn i64_ids() {
+        results_reach_teams(i64::MAX, 0i64);
+    }
+
+    #[test]
+    fn string_ids() {
+        results_reach_teams("red".to_owned(), "blue".to_owned());
+    }
+
+    #[test]
+    fn call_depth() {
+        let mut engine = RhaiScriptingEngine::new::<StepRng>();
+        engine.set_limits(ScriptLimits {
+            operations: 0,
+            call_depth: 0,
+            time: None,
+            ..Default::default()
+        });
+
+        let ast = engine
+            .engine
+            .compile("fn recurse(n) { recurse(n + 1) }")
+            .unwrap();
+
+        // a depth of 0 still stops a script recursing forever
+        let result = engine
+            .engine
+            .call_fn::<Dynamic>(&mut engine.scope.clone(), &ast, "recurse", (0 as INT,))
+            .map_err(RhaiScriptError::from);
+
+        assert!(matches!(
+            result,
+            Err(RhaiScriptError::Limit(err)) if matches!(*err, EvalAltResult::ErrorStackOverflow(..))
+        ));
+    }
+
+    fn battle_move(id: MoveId) -> BattleMove {
+        BattleMove {
+            id,
+            category: MoveCategory::Physical,
+            pokemon_type: PokemonType::Normal,
+            accuracy: None,
+            power: None,
+            priority: 0,
+            target: MoveTarget::Opponent,
+            contact: Default::default(),
+            crit_rate: 0,
+        }
+    }
+
+    /// Use a move from the first team on the second.
+    fn execute(
+        engine: &RhaiScriptingEngine,
+        m: &BattleMove,
+        players: &mut PlayerQuery<u8, ()>,
+    ) -> Result<Vec<Indexed<u8, PublicAction>>, RhaiScriptError> {
+        let field = Field::default();
+        let (abilities, held) = (Default::default(), Default::default());
+        let effects = Effects {
+            field: &field,
+            abilities: &abilities,
+            held: &held,
+        };
+
+        engine.execute_move(
+            &mut (),
+            &mut StepRng::new(0, 0),
+            &mut BattleData::default(),
+            effects,
+            m,
+            &TeamIndex(1, 0),
+            vec![TeamIndex(2, 0)],
+            players,
+        )
+    }
+
+    fn hp(players: &PlayerQuery<u8, ()>, pokemon: &TeamIndex<u8>) -> Health {
+        players
+            .get(pokemon.team())
+            .and_then(|p| p.party.active(pokemon.index()))
+            .map(|p| p.hp)
+            .unwrap()
+    }
+
+    #[test]
+    fn register_moves() {
+        let mut engine = RhaiScriptingEngine::new::<StepRng>();
+        let [tackle, ember, growl] =
+            ["tackle", "ember", "growl"].map(|id| id.parse::<MoveId>().unwrap());
+
+        let errors = engine
+            .register_moves([
+                (
+                    tackle,
+                    r#"
+                    fn use_move(m, user, targets) {
+                        let results = [];
+                        for target in targets {
+                            results.push(Heal(target, -10));
+                        }
+                        results
+                    }
+                    "#,
+                ),
+                (ember, "fn use_move(m, user, targets) {"),
+                (growl, "fn use_move(m user targets) { [] }"),
+            ])
+            .unwrap_err();
+
+        // every broken script is reported up front, and the rest are still registered
+        assert_eq!(errors.len(), 2);
+        assert!(errors
+            .iter()
+            .all(|err| matches!(err, RhaiScriptError::Parse(id, ..) if id != &tackle)));
+        assert!(engine.moves.contains_key(&tackle));
+
+        // one compiled script runs in any number of battles
+        let target = TeamIndex(2, 0);
+        for _ in 0..2 {
+            let mut players = players();
+            let before = hp(&players, &target);
+            let results = execute(&engine, &battle_move(tackle), &mut players).unwrap();
+            assert_eq!(results.len(), 1);
+            assert_eq!(hp(&players, &target), before - 10);
+        }
+
+        assert!(matches!(
+            execute(&engine, &battle_move(ember), &mut players()),
+            Err(RhaiScriptError::Missing(id)) if id == ember
+        ));
+    }
+
+    #[test]
+    fn items() {
+        let mut engine = RhaiScriptingEngine::new::<StepRng>();
+        let [potion, ether] = ["potion", "ether"].map(|id| id.parse::<ItemId>().unwrap());
+
+        engine
+            .register_items([(
+                potion,
+                r#"
+                fn use_item(item, player, target) {
+                    if item.id == "potion" && player.active.len() == 1 {
+                        [Heal(target, 20)]
+                    } else {
+                        [Fail(target)]
+                    }
+                }
+                "#,
+            )])
+            .unwrap();
+
+        let field = Field::default();
+        let (abilities, held) = (Default::default(), Default::default());
+        let effects = Effects {
+            field: &field,
+            abilities: &abilities,
+            held: &held,
+        };
+
+        let mut players = players();
+        let target = TeamIndex(1, 0);
+        let max = hp(&players, &target);
+
+        if let Some(pokemon) = players.get_mut(&1).and_then(|p| p.party.active_mut(0)) {
+            pokemon.hp = 1;
+        }
+
+        let mut run = |item: ItemId| {
+            engine.execute_item(
+                &mut (),
+                &mut StepRng::new(0, 0),
+                &mut BattleData::default(),
+                effects,
+                &item,
+                &1,
+                target.clone(),
+                &mut players,
+            )
+        };
+
+        let results = run(potion).unwrap();
+        assert!(matches!(
+            results.as_slice(),
+            [Indexed(pokemon, PublicAction::SetHP(..))] if pokemon == &target
+        ));
+
+        assert!(matches!(
+            run(ether),
+            Err(RhaiScriptError::MissingItem(id)) if id == ether
+        ));
+
+        assert_eq!(hp(&players, &target), 21.min(max));
+        // the used item is taken from the bag, which never had one here
+        assert!(!players.get(&1).unwrap().bag.contains(&potion));
+    }
+
+    #[test]
+    fn pokemon_properties() {
+        let engine = RhaiScriptingEngine::new::<StepRng>();
+        let teams = ScriptTeams::new([1u8, 2]);
+
+        let mut pokemon = pokemon();
+        pokemon.hp = pokemon.max_hp() / 2;
+        pokemon.stages[BattleStatType::Basic(StatType::Attack)] = 2;
+        pokemon.ailment =
+            Some(AilmentLength::Permanent.init(Ailment::Poison, &mut StepRng::new(0, 0)));
+
+        let user = ScriptPokemon::new(
+            teams.slot(&TeamIndex(1, 0)).unwrap(),
+            &pokemon,
+            Default::default(),
+        );
+        let target = ScriptPokemon::new(
+            teams.slot(&TeamIndex(2, 0)).unwrap(),
+            &pokemon,
+            Default::default(),
+        );
+
+        let mut scope = engine.scope.clone();
+        scope.push("user", user);
+        scope.push("target", target);
+        scope.push(
+            "fire",
+            ScriptMove {
+                category: MoveCategory::Special,
+                type_: PokemonType::Fire,
+                accuracy: None,
+                crit_rate: 0,
+            },
+        );
+
+        let mut eval = |script: &str| {
+            engine
+                .engine
+                .eval_with_scope::<Dynamic>(&mut scope, script)
+                .unwrap()
+        };
+
+        assert_eq!(eval("user.level").as_int(), Ok(pokemon.level as INT));
+        assert_eq!(eval("user.max_hp").as_int(), Ok(pokemon.max_hp() as INT));
+        assert_eq!(
+            eval("user.percent_hp").as_float(),
+            Ok(pokemon.percent_hp() as FLOAT)
+        );
+        assert_eq!(eval("user.types.len()").as_int(), Ok(2));
+        assert_eq!(
+            eval("user.stat(ATTACK)").as_int(),
+            Ok(pokemon.stat(StatType::Attack) as INT)
+        );
+        assert_eq!(eval("user.stage(ATTACK)").as_int(), Ok(2));
+        assert_eq!(eval("user.stage(SPEED)").as_int(), Ok(0));
+        assert_eq!(eval("user.stat(ACCURACY)").as_int(), Ok(0));
+        assert_eq!(
+            eval("user.has_ailment && user.ailment == POISON").as_bool(),
+            Ok(true)
+        );
+        assert!(eval("user.item").is::<()>());
+        assert!(eval("user.ability").is::<()>());
+        assert_eq!(eval("user.moves.len()").as_int(), Ok(0));
+        assert_eq!(eval("user.fainted").as_bool(), Ok(false));
+
+        // the same species on another team is not the user
+        assert_eq!(eval("user.is(user)").as_bool(), Ok(true));
+        assert_eq!(eval("user.is(target)").as_bool(), Ok(false));
+
+        // fire is super effective against grass
+        assert_eq!(
+            eval("fire.effective(target).multiplier").as_float(),
+            Ok(2.0)
+        );
+    }
+
+    #[test]
+    fn result_constructors() {
+        let engine = RhaiScriptingEngine::new::<StepRng>();
+        let teams = ScriptTeams::new([1u8, 2]);
+        let pokemon = pokemon();
+        let (user, target) = (TeamIndex(1, 0), TeamIndex(2, 0));
+
+        let results = |script: &str| {
+            let ast = engine
+                .engine
+                .compile_with_scope(&engine.scope, script)
+                .unwrap();
+            let args = (
+                ScriptPokemon::new(teams.slot(&user).unwrap(), &pokemon, Default::default()),
+                ScriptPokemon::new(teams.slot(&target).unwrap(), &pokemon, Default::default()),
+            );
+            engine
+                .engine
+                .call_fn::<Array>(&mut engine.scope.clone(), &ast, "use_move", args)
+                .map(|results| {
+                    results
+                        .into_iter()
+                        .flat_map(Dynamic::try_cast::<ScriptActionResult>)
+                        .flat_map(|r| teams.result(r))
+                        .collect::<Vec<_>>()
+                })
+                .map_err(RhaiScriptError::from)
+        };
+
+        let attack = BattleStatType::Basic(StatType::Attack);
+        let speed = BattleStatType::Basic(StatType::Speed);
+
+        let actions = results(
+            r#"
+            fn use_move(user, target) {
+                let hit = damage(30);
+                hit.damage /= 2;
+                [
+                    Stat(target, "attack", -1),
+                    Stat(user, "speed", 2),
+                    Flinch(target),
+                    Damage(target, hit),
+                    Volatile(target, "confusion"),
+                    ClearWeather(user),
+                    Side(user, "tailwind"),
+                    Hazard(target, "spikes"),
+                    Fail(user),
+                ]
+            }
+            "#,
+        )
+        .unwrap();
+
+        let actions = actions
+            .iter()
+            .map(|Indexed(pokemon, action)| (pokemon == &user, action))
+            .collect::<Vec<_>>();
+
+        assert!(matches!(
+            actions.as_slice(),
+            [
+                (false, ActionResult::Stat(a, -1)),
+                (true, ActionResult::Stat(s, 2)),
+                (false, ActionResult::Volatile(VolatileStatus::Flinch, true)),
+                (false, ActionResult::Damage(damage)),
+                (false, ActionResult::Volatile(VolatileStatus::Confusion, true)),
+                (true, ActionResult::Weather(None)),
+                (true, ActionResult::Side(SideEvent::Start(SideCondition::Tailwind))),
+                (false, ActionResult::Side(SideEvent::Hazard(Hazard::Spikes))),
+                (true, ActionResult::Fail),
+            ] if a == &attack && s == &speed && damage.damage == 15
+        ));
+
+        // names that mean nothing are errors instead of being ignored
+        for script in [
+            r#"fn use_move(user, target) { [Stat(target, "luck", 1)] }"#,
+            r#"fn use_move(user, target) { [Volatile(target, "sleepy")] }"#,
+            r#"fn use_move(user, target) { [Side(user, "sunshine")] }"#,
+        ] {
+            assert!(matches!(
+                results(script),
+                Err(RhaiScriptError::Evaluate(..))
+            ));
+        }
+    }
+}
@@ -1,5 +1,3 @@
-use core::ops::DerefMut;
-
 use battle::pokedex::ailment::*;
 use rand::Rng;
 
@@ -18,12 +16,12 @@ impl ScriptAilmentEffect {
 
     pub fn init<R: Rng + Clone + Send + Sync + 'static>(
         &mut self,
-        mut random: ScriptRandom<R>,
+        random: ScriptRandom<R>,
     ) -> LiveScriptAilment {
-        let random = random.deref_mut();
+        let mut random = random.get();
         ScriptAilment(
             self.0
-                .map(|ailment| ailment.turns.init(ailment.ailment, random)),
+                .map(|ailment| ailment.turns.init(ailment.ailment, &mut *random)),
         )
     }
 }
@@ -2,9 +2,11 @@ use rhai::INT;
 
 use battle::{
     moves::{Accuracy, BattleMove, MoveCategory},
-    pokedex::types::PokemonType,
+    pokedex::types::{Effective, PokemonType},
 };
 
+use super::ScriptPokemon;
+
 #[derive(Clone, Copy)]
 pub struct ScriptMove {
     pub category: MoveCategory,
@@ -32,4 +34,9 @@ impl ScriptMove {
     pub fn get_crit_rate(&mut self) -> INT {
         self.crit_rate
     }
+
+    /// How effective this move is against a pokemon.
+    pub fn effective(&mut self, target: ScriptPokemon) -> Effective {
+        self.category.effective(self.type_, target.pokemon.types)
+    }
 }
@@ -1,67 +1,49 @@
-use core::ops::{Deref, DerefMut};
+use core::ops::Deref;
+use std::sync::Arc;
 
 use rand::Rng;
-use rhai::INT;
+use rhai::{Array, Dynamic, FLOAT, INT};
 
 use battle::{
     pokedex::types::PokemonType,
     pokemon::{crit, throw_move, BattlePokemon},
-    moves::MoveCategory,
-    pokemon::{Indexed, TeamIndex},
+    moves::{DamageModifiers, MoveCategory},
+    pokemon::{
+        stat::{BattleStatType, StatStages},
+        TeamIndex,
+    },
 };
 
 use super::{ScriptDamage, ScriptMove, ScriptRandom};
 
-#[derive(Debug)]
-pub struct DerefPtr<T>(*const T);
-
-impl<T> DerefPtr<T> {
-    pub fn of(t: &T) -> Self {
-        Self(t as *const T)
-    }
-}
-
-impl<T> Deref for DerefPtr<T> {
-    type Target = T;
-
-    fn deref(&self) -> &Self::Target {
-        unsafe { &*self.0 }
-    }
-}
-
-impl<T> From<&T> for DerefPtr<T> {
-    fn from(t: &T) -> Self {
-        Self(t as _)
-    }
-}
-
-impl<T> Clone for DerefPtr<T> {
-    fn clone(&self) -> Self {
-        Self(self.0)
-    }
-}
-
-impl<T> Copy for DerefPtr<T> {}
-
+/// A pokemon as it was when the script was called, along with its position on the field
+/// and the modifiers of damage it takes.
+/// Scripts change pokemon through the results they return.
+/// The results are only applied after the script returns and each call starts from a fresh scope,
+/// so a snapshot cannot differ from the pokemon while a script can see it.
+/// Its team is a slot given by [ScriptTeams](crate::scripting::ScriptTeams).
 #[derive(Debug, Clone)]
-pub struct ScriptPokemon<ID: Clone + Send + Sync + 'static>(TeamIndex<ID>, *mut BattlePokemon);
-
-impl<ID: Clone + Send + Sync + 'static> ScriptPokemon<ID> {
-    pub fn new(pokemon: Indexed<ID, &mut BattlePokemon>) -> Self {
-        let Indexed(id, pokemon) = pokemon;
-        Self(id, pokemon as _)
+pub struct ScriptPokemon(TeamIndex<usize>, Arc<BattlePokemon>, DamageModifiers);
+
+impl ScriptPokemon {
+    pub fn new(
+        position: TeamIndex<usize>,
+        pokemon: &BattlePokemon,
+        modifiers: DamageModifiers,
+    ) -> Self {
+        Self(position, Arc::new(pokemon.clone()), modifiers)
     }
 
-    pub fn position(&self) -> &TeamIndex<ID> {
+    pub fn position(&self) -> &TeamIndex<usize> {
         &self.0
     }
 
     pub fn throw_move<R: Rng + Clone + Send + Sync + 'static>(
         &mut self,
-        mut random: ScriptRandom<R>,
+        random: ScriptRandom<R>,
         m: ScriptMove,
     ) -> bool {
-        throw_move(random.deref_mut(), m.accuracy)
+        throw_move(&mut *random.get(), m.accuracy)
     }
 
     // pub fn ailment_affects(
@@ -80,41 +62,125 @@ impl<ID: Clone + Send + Sync + 'static> ScriptPokemon<ID> {
         move_type: PokemonType,
         crit_rate: INT,
     ) -> ScriptDamage {
-        let mut random = random;
-        let crit = crit(random.deref_mut(), crit_rate as _);
+        let mut random = random.get();
+        let crit = crit(&mut *random, crit_rate as _);
         ScriptDamage::from(self.move_power_damage_random(
-            random.deref_mut(),
+            &mut *random,
             &target,
             power as _,
             category,
             move_type,
             crit,
+            &target.2.clone().attacker(&self.2),
         ))
     }
     pub fn hp(&mut self) -> INT {
         self.hp as _
     }
-}
 
-unsafe impl<ID: Clone + Send + Sync + 'static> Send for ScriptPokemon<ID> {}
-unsafe impl<ID: Clone + Send + Sync + 'static> Sync for ScriptPokemon<ID> {}
+    pub fn max_hp(&mut self) -> INT {
+        self.deref().max_hp() as _
+    }
 
-impl<ID: Clone + Send + Sync + 'static> Deref for ScriptPokemon<ID> {
-    type Target = BattlePokemon;
+    /// HP as a fraction of max HP, from 0.0 to 1.0
+    pub fn percent_hp(&mut self) -> FLOAT {
+        self.deref().percent_hp() as _
+    }
 
-    fn deref(&self) -> &Self::Target {
-        unsafe { &*self.1 }
+    pub fn level(&mut self) -> INT {
+        self.level as _
+    }
+
+    pub fn types(&mut self) -> Array {
+        let types = self.pokemon.types;
+        core::iter::once(types.primary)
+            .chain(types.secondary)
+            .map(Dynamic::from)
+            .collect()
+    }
+
+    /// A stat after its stage is applied. Accuracy and evasion have no value.
+    pub fn stat(&mut self, stat: BattleStatType) -> INT {
+        match stat {
+            BattleStatType::Basic(stat) => self.deref().stat(stat) as _,
+            _ => 0,
+        }
+    }
+
+    pub fn stage(&mut self, stat: BattleStatType) -> INT {
+        self.stages[stat] as _
+    }
+
+    pub fn stages(&mut self) -> StatStages {
+        self.stages.clone()
+    }
+
+    /// The pokemon's ailment, or `()` if it has none.
+    pub fn ailment(&mut self) -> Dynamic {
+        self.deref()
+            .ailment
+            .map(|a| Dynamic::from(a.ailment))
+            .unwrap_or(Dynamic::UNIT)
+    }
+
+    pub fn has_ailment(&mut self) -> bool {
+        self.deref().ailment.is_some()
+    }
+
+    /// Id of the held item, or `()` if there is none.
+    pub fn item(&mut self) -> Dynamic {
+        self.deref()
+            .item
+            .as_ref()
+            .map(|item| Dynamic::from(item.id.as_str().to_owned()))
+            .unwrap_or(Dynamic::UNIT)
+    }
+
+    /// Id of the ability, or `()` if there is none.
+    pub fn ability(&mut self) -> Dynamic {
+        self.deref()
+            .ability
+            .as_ref()
+            .map(|ability| Dynamic::from(ability.to_string()))
+            .unwrap_or(Dynamic::UNIT)
+    }
+
+    /// Ids of known moves
+    pub fn moves(&mut self) -> Array {
+        self.deref()
+            .moves
+            .iter()
+            .map(|m| Dynamic::from(m.id().0.to_string()))
+            .collect()
+    }
+
+    pub fn fainted(&mut self) -> bool {
+        self.deref().fainted()
+    }
+
+    /// The weather the pokemon is in, or `()` if there is none.
+    pub fn weather(&mut self) -> Dynamic {
+        self.2.weather.map(Dynamic::from).unwrap_or(Dynamic::UNIT)
     }
 }
 
-impl<ID: Clone + Send + Sync + 'static> DerefMut for ScriptPokemon<ID> {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        unsafe { &mut *self.1 }
+impl ScriptPokemon {
+    /// If both are the same pokemon on the field, i.e. the target is the user.
+    pub fn is(&mut self, other: Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl Deref for ScriptPokemon {
+    type Target = BattlePokemon;
+
+    fn deref(&self) -> &Self::Target {
+        &self.1
     }
 }
 
-impl<ID: Clone + Send + Sync + 'static> Into<TeamIndex<ID>> for ScriptPokemon<ID> {
-    fn into(self) -> TeamIndex<ID> {
+impl Into<TeamIndex<usize>> for ScriptPokemon {
+    fn into(self) -> TeamIndex<usize> {
         self.0
     }
 }
@@ -1,35 +1,30 @@
-use core::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
 
 use rand::Rng;
 use rhai::INT;
 
-#[derive(Clone, Copy)]
-pub struct ScriptRandom<R: Rng + Clone + Send + Sync + 'static>(*mut R);
-
-unsafe impl<R: Rng + Clone + Send + Sync + 'static> Send for ScriptRandom<R> {}
-
-unsafe impl<R: Rng + Clone + Send + Sync + 'static> Sync for ScriptRandom<R> {}
+/// Random number generator shared with a script.
+///
+/// Scripts draw from a copy of the battle's generator,
+/// which is written back with [ScriptRandom::finish] once they return.
+#[derive(Clone)]
+pub struct ScriptRandom<R: Rng + Clone + Send + Sync + 'static>(Arc<Mutex<R>>);
 
 impl<R: Rng + Clone + Send + Sync + 'static> ScriptRandom<R> {
-    pub fn new(random: &mut R) -> Self {
-        Self(random as _)
+    pub fn new(random: &R) -> Self {
+        Self(Arc::new(Mutex::new(random.clone())))
     }
 
-    pub fn chance(&mut self, percent: INT) -> bool {
-        self.deref_mut().gen_bool(percent as f64 / 100.0)
+    pub fn get(&self) -> MutexGuard<'_, R> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
     }
-}
 
-impl<R: Rng + Clone + Send + Sync + 'static> Deref for ScriptRandom<R> {
-    type Target = R;
-
-    fn deref(&self) -> &Self::Target {
-        unsafe { &*self.0 }
+    /// Continue the battle's generator from where the script left off.
+    pub fn finish(&self, random: &mut R) {
+        *random = self.get().clone();
     }
-}
 
-impl<R: Rng + Clone + Send + Sync + 'static> DerefMut for ScriptRandom<R> {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        unsafe { &mut *self.0 }
+    pub fn chance(&mut self, percent: INT) -> bool {
+        self.get().gen_bool(percent as f64 / 100.0)
     }
 }
@@ -1,32 +1,197 @@
-use rhai::INT;
+use rhai::{EvalAltResult, INT};
 
-use battle::{engine::ActionResult, pokemon::Indexed};
+use battle::{
+    data::{BattleWeather, Weather},
+    engine::ActionResult,
+    pokedex::pokemon::stat::StatType,
+    pokemon::{stat::BattleStatType, volatile::VolatileStatus, Indexed},
+    side::{Hazard, SideCondition, SideEvent},
+};
 
 use super::{damage::ScriptDamage, pokemon::ScriptPokemon, LiveScriptAilment};
 
+/// A result for the pokemon at a team slot given by [ScriptTeams](crate::scripting::ScriptTeams).
 #[derive(Clone, Copy)]
-pub struct ScriptActionResult<ID: Clone + Send + Sync + 'static>(pub Indexed<ID, ActionResult>);
+pub struct ScriptActionResult(pub Indexed<usize, ActionResult>);
 
-impl<ID: Clone + Send + Sync + 'static> ScriptActionResult<ID> {
-    pub fn new(pokemon: ScriptPokemon<ID>, action: ActionResult) -> Self {
+impl ScriptActionResult {
+    pub fn new(pokemon: ScriptPokemon, action: ActionResult) -> Self {
         Self(Indexed(pokemon.position().clone(), action))
     }
 
-    pub fn damage(pokemon: ScriptPokemon<ID>, damage: ScriptDamage) -> Self {
+    pub fn damage(pokemon: ScriptPokemon, damage: ScriptDamage) -> Self {
         Self::new(pokemon, ActionResult::Damage(damage.into()))
     }
 
-    pub fn heal(pokemon: ScriptPokemon<ID>, heal: INT) -> Self {
+    pub fn heal(pokemon: ScriptPokemon, heal: INT) -> Self {
         Self::new(pokemon, ActionResult::Heal(heal as _))
     }
 
-    pub fn ailment(pokemon: ScriptPokemon<ID>, ailment: LiveScriptAilment) -> Self {
+    pub fn ailment(pokemon: ScriptPokemon, ailment: LiveScriptAilment) -> Self {
         Self::new(pokemon, ActionResult::Ailment(ailment.0))
     }
 
-    // pub const fn Status(effect: StatusEffect) -> MoveResult { MoveResult::Status(effect) }
+    /// Badly poison a pokemon after it is poisoned.
+    pub fn toxic(pokemon: ScriptPokemon) -> Self {
+        Self::new(pokemon, ActionResult::Toxic)
+    }
+
+    pub fn stat(pokemon: ScriptPokemon, stat: BattleStatType, stage: INT) -> Self {
+        Self::new(pokemon, ActionResult::Stat(stat, stage as _))
+    }
+
+    /// Change a stat by its name, such as `"attack"` or `"sp_defense"`.
+    pub fn stat_named(
+        pokemon: ScriptPokemon,
+        stat: &str,
+        stage: INT,
+    ) -> Result<Self, Box<EvalAltResult>> {
+        let stat = match stat {
+            "attack" => BattleStatType::Basic(StatType::Attack),
+            "defense" => BattleStatType::Basic(StatType::Defense),
+            "sp_attack" => BattleStatType::Basic(StatType::SpAttack),
+            "sp_defense" => BattleStatType::Basic(StatType::SpDefense),
+            "speed" => BattleStatType::Basic(StatType::Speed),
+            "accuracy" => BattleStatType::Accuracy,
+            "evasion" => BattleStatType::Evasion,
+            stat => return Err(format!("Unknown stat {}", stat).into()),
+        };
+        Ok(Self::stat(pokemon, stat, stage))
+    }
+
+    pub fn cancel(pokemon: ScriptPokemon, reason: &str) -> Result<Self, Box<EvalAltResult>> {
+        let reason = reason
+            .parse()
+            .map_err(|_| format!("Invalid cancel reason {}", reason))?;
+        Ok(Self::new(pokemon, ActionResult::Cancel(reason)))
+    }
+
+    pub fn flinch(pokemon: ScriptPokemon) -> Self {
+        Self::new(
+            pokemon,
+            ActionResult::Volatile(VolatileStatus::Flinch, true),
+        )
+    }
+
+    /// Give the pokemon a volatile status, such as `"confusion"`.
+    pub fn volatile(pokemon: ScriptPokemon, status: &str) -> Result<Self, Box<EvalAltResult>> {
+        let status = volatile_status(status)?;
+        Ok(Self::new(pokemon, ActionResult::Volatile(status, true)))
+    }
+
+    pub fn end_volatile(pokemon: ScriptPokemon, status: &str) -> Result<Self, Box<EvalAltResult>> {
+        let status = volatile_status(status)?;
+        Ok(Self::new(pokemon, ActionResult::Volatile(status, false)))
+    }
+
+    pub fn reveal(pokemon: ScriptPokemon, full: bool) -> Self {
+        Self::new(pokemon, ActionResult::Reveal(full))
+    }
+
+    pub fn remove(pokemon: ScriptPokemon, reason: &str) -> Result<Self, Box<EvalAltResult>> {
+        let reason = reason
+            .parse()
+            .map_err(|_| format!("Invalid removal reason {}", reason))?;
+        Ok(Self::new(pokemon, ActionResult::Remove(reason)))
+    }
+
+    /// Start a weather caused by the pokemon for a number of turns.
+    pub fn weather(pokemon: ScriptPokemon, weather: Weather, turns: INT) -> Self {
+        let weather = BattleWeather::new(weather, Some(turns.clamp(1, u8::MAX as _) as _));
+        Self::new(pokemon, ActionResult::Weather(Some(weather)))
+    }
+
+    pub fn clear_weather(pokemon: ScriptPokemon) -> Self {
+        Self::new(pokemon, ActionResult::Weather(None))
+    }
 
-    pub fn miss(pokemon: ScriptPokemon<ID>) -> Self {
+    /// Start a field condition registered with the engine, such as `"trick_room"`.
+    pub fn field(pokemon: ScriptPokemon, id: &str) -> Result<Self, Box<EvalAltResult>> {
+        let id = id
+            .parse()
+            .map_err(|_| format!("Invalid field condition {}", id))?;
+        Ok(Self::new(pokemon, ActionResult::Field(id, true)))
+    }
+
+    pub fn end_field(pokemon: ScriptPokemon, id: &str) -> Result<Self, Box<EvalAltResult>> {
+        let id = id
+            .parse()
+            .map_err(|_| format!("Invalid field condition {}", id))?;
+        Ok(Self::new(pokemon, ActionResult::Field(id, false)))
+    }
+
+    /// Start a condition on the side of the pokemon, such as `"reflect"`.
+    pub fn side(pokemon: ScriptPokemon, condition: &str) -> Result<Self, Box<EvalAltResult>> {
+        let condition = side_condition(condition)?;
+        Ok(Self::new(
+            pokemon,
+            ActionResult::Side(SideEvent::Start(condition)),
+        ))
+    }
+
+    pub fn end_side(pokemon: ScriptPokemon, condition: &str) -> Result<Self, Box<EvalAltResult>> {
+        let condition = side_condition(condition)?;
+        Ok(Self::new(
+            pokemon,
+            ActionResult::Side(SideEvent::End(condition)),
+        ))
+    }
+
+    /// Add a layer of a hazard to the side of the pokemon, such as `"spikes"`.
+    pub fn hazard(pokemon: ScriptPokemon, hazard: &str) -> Result<Self, Box<EvalAltResult>> {
+        let hazard = side_hazard(hazard)?;
+        Ok(Self::new(
+            pokemon,
+            ActionResult::Side(SideEvent::Hazard(hazard)),
+        ))
+    }
+
+    pub fn clear_hazard(pokemon: ScriptPokemon, hazard: &str) -> Result<Self, Box<EvalAltResult>> {
+        let hazard = side_hazard(hazard)?;
+        Ok(Self::new(
+            pokemon,
+            ActionResult::Side(SideEvent::Clear(hazard)),
+        ))
+    }
+
+    pub fn fail(pokemon: ScriptPokemon) -> Self {
+        Self::new(pokemon, ActionResult::Fail)
+    }
+
+    pub fn miss(pokemon: ScriptPokemon) -> Self {
         Self::new(pokemon, ActionResult::Miss)
     }
 }
+
+fn side_condition(condition: &str) -> Result<SideCondition, Box<EvalAltResult>> {
+    Ok(match condition {
+        "reflect" => SideCondition::Reflect,
+        "light_screen" => SideCondition::LightScreen,
+        "tailwind" => SideCondition::Tailwind,
+        "safeguard" => SideCondition::Safeguard,
+        condition => return Err(format!("Unknown side condition {}", condition).into()),
+    })
+}
+
+fn side_hazard(hazard: &str) -> Result<Hazard, Box<EvalAltResult>> {
+    Ok(match hazard {
+        "spikes" => Hazard::Spikes,
+        "stealth_rock" => Hazard::StealthRock,
+        "toxic_spikes" => Hazard::ToxicSpikes,
+        hazard => return Err(format!("Unknown hazard {}", hazard).into()),
+    })
+}
+
+fn volatile_status(status: &str) -> Result<VolatileStatus, Box<EvalAltResult>> {
+    Ok(match status {
+        "confusion" => VolatileStatus::Confusion,
+        "leech_seed" => VolatileStatus::LeechSeed,
+        "substitute" => VolatileStatus::Substitute,
+        "curse" => VolatileStatus::Curse,
+        "infatuation" => VolatileStatus::Infatuation,
+        "focus_energy" => VolatileStatus::FocusEnergy,
+        "perish_song" => VolatileStatus::PerishSong,
+        "flinch" => VolatileStatus::Flinch,
+        status => return Err(format!("Unknown volatile status {}", status).into()),
+    })
+}
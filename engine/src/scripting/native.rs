@@ -0,0 +1,488 @@
+use core::{
+    fmt::{Display, Formatter, Result as FmtResult},
+    hash::Hash,
+};
+use std::error::Error;
+
+use hashbrown::HashMap;
+use rand::{Rng, RngCore};
+
+use battle::{
+    data::BattleData,
+    engine::{ActionResult, PlayerQuery},
+    moves::BattleMove,
+    pokedex::{item::ItemId, moves::MoveId},
+    pokemon::{BattlePokemon, Indexed, TeamIndex},
+    select::PublicAction,
+};
+
+use crate::effects::Effects;
+
+use super::{ScriptingEngine, Trigger, TriggerSource};
+
+/// What a native move gets to look at when it is used.
+pub struct MoveContext<'a, ID> {
+    pub battle: &'a BattleData,
+    pub effects: Effects<'a>,
+    pub random: &'a mut dyn RngCore,
+    pub m: &'a BattleMove,
+    pub user: Indexed<ID, &'a BattlePokemon>,
+    pub targets: Vec<Indexed<ID, &'a BattlePokemon>>,
+}
+
+/// What a native item gets to look at when it is used.
+pub struct ItemContext<'a, ID> {
+    pub battle: &'a BattleData,
+    pub effects: Effects<'a>,
+    pub random: &'a mut dyn RngCore,
+    pub item: &'a ItemId,
+    pub user: &'a ID,
+    pub target: Indexed<ID, &'a BattlePokemon>,
+}
+
+/// What the passive effect of an ability or held item gets to look at when it is triggered.
+pub struct TriggerContext<'a, ID> {
+    pub battle: &'a BattleData,
+    pub effects: Effects<'a>,
+    pub random: &'a mut dyn RngCore,
+    pub trigger: Trigger,
+    pub source: &'a TriggerSource,
+    pub pokemon: Indexed<ID, &'a BattlePokemon>,
+    pub other: Option<Indexed<ID, &'a BattlePokemon>>,
+}
+
+pub type NativeMove<ID> =
+    Box<dyn Fn(&mut MoveContext<'_, ID>) -> Vec<Indexed<ID, ActionResult>> + Send + Sync>;
+pub type NativeItem<ID> =
+    Box<dyn Fn(&mut ItemContext<'_, ID>) -> Vec<Indexed<ID, ActionResult>> + Send + Sync>;
+pub type NativeTrigger<ID> =
+    Box<dyn Fn(&mut TriggerContext<'_, ID>) -> Vec<Indexed<ID, ActionResult>> + Send + Sync>;
+
+/// Moves and items written in Rust.
+pub struct NativeScriptingEngine<ID> {
+    pub moves: HashMap<MoveId, NativeMove<ID>>,
+    pub items: HashMap<ItemId, NativeItem<ID>>,
+    /// Passive effects of abilities and held items by the event they run for
+    pub triggers: HashMap<(TriggerSource, Trigger), NativeTrigger<ID>>,
+}
+
+#[derive(Debug)]
+pub enum NativeScriptError {
+    MissingMove(MoveId),
+    MissingItem(ItemId),
+}
+
+impl<ID> NativeScriptingEngine<ID> {
+    pub fn new() -> Self {
+        Self {
+            moves: Default::default(),
+            items: Default::default(),
+            triggers: Default::default(),
+        }
+    }
+
+    pub fn register_move(
+        &mut self,
+        id: MoveId,
+        f: impl Fn(&mut MoveContext<'_, ID>) -> Vec<Indexed<ID, ActionResult>> + Send + Sync + 'static,
+    ) {
+        self.moves.insert(id, Box::new(f));
+    }
+
+    pub fn register_item(
+        &mut self,
+        id: ItemId,
+        f: impl Fn(&mut ItemContext<'_, ID>) -> Vec<Indexed<ID, ActionResult>> + Send + Sync + 'static,
+    ) {
+        self.items.insert(id, Box::new(f));
+    }
+
+    pub fn register_trigger(
+        &mut self,
+        source: TriggerSource,
+        trigger: Trigger,
+        f: impl Fn(&mut TriggerContext<'_, ID>) -> Vec<Indexed<ID, ActionResult>>
+            + Send
+            + Sync
+            + 'static,
+    ) {
+        self.triggers.insert((source, trigger), Box::new(f));
+    }
+}
+
+impl<ID> Default for NativeScriptingEngine<ID> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<ID: Eq + Hash + Clone + Send + Sync + 'static, T> ScriptingEngine<ID, T>
+    for NativeScriptingEngine<ID>
+{
+    type Data = ();
+
+    type ExecutionError = NativeScriptError;
+
+    fn execute_move(
+        &self,
+        _data: &mut Self::Data,
+        random: &mut (impl Rng + Clone + Send + Sync + 'static),
+        battle: &mut BattleData,
+        effects: Effects,
+        m: &BattleMove,
+        user: &TeamIndex<ID>,
+        targets: Vec<TeamIndex<ID>>,
+        players: &mut PlayerQuery<ID, T>,
+    ) -> Result<Vec<Indexed<ID, PublicAction>>, Self::ExecutionError> {
+        let f = self
+            .moves
+            .get(&m.id)
+            .ok_or(NativeScriptError::MissingMove(m.id))?;
+
+        let results = {
+            let user = match active(players, user) {
+                Some(user) => user,
+                None => return Ok(Vec::new()),
+            };
+
+            let targets = targets
+                .iter()
+                .flat_map(|target| active(players, target))
+                .collect();
+
+            f(&mut MoveContext {
+                battle,
+                effects,
+                random,
+                m,
+                user,
+                targets,
+            })
+        };
+
+        let mut actions = Vec::new();
+
+        for action in results {
+            if crate::on_field(players, &action.0) {
+                crate::run_action(action, random, battle, effects, user, &mut actions, players);
+            }
+        }
+
+        Ok(actions)
+    }
+
+    fn execute_item(
+        &self,
+        _data: &mut Self::Data,
+        random: &mut (impl Rng + Clone + Send + Sync + 'static),
+        battle: &mut BattleData,
+        effects: Effects,
+        item: &ItemId,
+        user: &ID,
+        target: TeamIndex<ID>,
+        players: &mut PlayerQuery<ID, T>,
+    ) -> Result<Vec<Indexed<ID, PublicAction>>, Self::ExecutionError> {
+        let f = self
+            .items
+            .get(item)
+            .ok_or(NativeScriptError::MissingItem(*item))?;
+
+        let results = match active(players, &target) {
+            Some(target) => f(&mut ItemContext {
+                battle,
+                effects,
+                random,
+                item,
+                user,
+                target,
+            }),
+            None => return Ok(Vec::new()),
+        };
+
+        let user_id = TeamIndex(
+            user.clone(),
+            players
+                .get(user)
+                .and_then(|p| p.party.active.iter().position(Option::is_some))
+                .unwrap_or_default(),
+        );
+
+        let mut actions = Vec::new();
+
+        for action in results {
+            if crate::on_field(players, &action.0) {
+                crate::run_action(action, random, battle, effects, &user_id, &mut actions, players);
+            }
+        }
+
+        if let Some(player) = players.get_mut(user) {
+            player.bag.try_take(item, 1);
+        }
+
+        Ok(actions)
+    }
+
+    fn execute_trigger(
+        &self,
+        _data: &mut Self::Data,
+        random: &mut (impl Rng + Clone + Send + Sync + 'static),
+        battle: &mut BattleData,
+        effects: Effects,
+        trigger: Trigger,
+        source: &TriggerSource,
+        pokemon: &TeamIndex<ID>,
+        party: usize,
+        other: Option<&TeamIndex<ID>>,
+        players: &mut PlayerQuery<ID, T>,
+    ) -> Result<Vec<Indexed<ID, PublicAction>>, Self::ExecutionError> {
+        let f = match self.triggers.get(&(*source, trigger)) {
+            Some(f) => f,
+            None => return Ok(Vec::new()),
+        };
+
+        let results = {
+            // a fainted pokemon has left the field, so it is found by its place in the party
+            let user = match players
+                .get(pokemon.team())
+                .and_then(|p| p.party.pokemon.get(party))
+            {
+                Some(user) => Indexed(pokemon.clone(), user),
+                None => return Ok(Vec::new()),
+            };
+
+            f(&mut TriggerContext {
+                battle,
+                effects,
+                random,
+                trigger,
+                source,
+                pokemon: user,
+                other: other.and_then(|other| active(players, other)),
+            })
+        };
+
+        let mut actions = Vec::new();
+
+        for action in results {
+            if crate::on_field(players, &action.0) {
+                crate::run_action(action, random, battle, effects, pokemon, &mut actions, players);
+            }
+        }
+
+        Ok(actions)
+    }
+}
+
+fn active<'a, ID: PartialEq + Clone, T>(
+    players: &'a PlayerQuery<ID, T>,
+    position: &TeamIndex<ID>,
+) -> Option<Indexed<ID, &'a BattlePokemon>> {
+    players
+        .get(position.team())
+        .and_then(|p| p.party.active(position.index()))
+        .map(|p| Indexed(position.clone(), p))
+}
+
+impl Error for NativeScriptError {}
+
+impl Display for NativeScriptError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::MissingMove(id) => write!(f, "No native move with id {}", &id.0),
+            Self::MissingItem(id) => write!(f, "No native item with id {}", id.as_str()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use rand::rngs::mock::StepRng;
+
+    use battle::{
+        data::BattleData,
+        engine::{ActionResult, PlayerQuery},
+        moves::{BattleMove, MoveCategory, MoveTarget},
+        pokedex::{pokemon::Health, types::PokemonType},
+        pokemon::{Indexed, TeamIndex},
+        select::PublicAction,
+    };
+
+    use crate::{effects::Effects, field::Field, tests::players};
+
+    use super::{
+        NativeScriptError, NativeScriptingEngine, ScriptingEngine, Trigger, TriggerSource,
+    };
+
+    fn hp(players: &PlayerQuery<u8, ()>, pokemon: &TeamIndex<u8>) -> Health {
+        players
+            .get(pokemon.team())
+            .and_then(|p| p.party.active(pokemon.index()))
+            .map(|p| p.hp)
+            .unwrap()
+    }
+
+    #[test]
+    fn moves() {
+        let id = "tackle".parse().unwrap();
+        let m = BattleMove {
+            id,
+            category: MoveCategory::Physical,
+            pokemon_type: PokemonType::Normal,
+            accuracy: None,
+            power: None,
+            priority: 0,
+            target: MoveTarget::Opponent,
+            contact: Default::default(),
+            crit_rate: 0,
+        };
+
+        let mut engine = NativeScriptingEngine::new();
+        engine.register_move(id, |context| {
+            context
+                .targets
+                .iter()
+                .map(|Indexed(target, ..)| Indexed(target.clone(), ActionResult::Heal(-10)))
+                .collect()
+        });
+
+        let field = Field::default();
+        let (abilities, held) = (Default::default(), Default::default());
+        let effects = Effects {
+            field: &field,
+            abilities: &abilities,
+            held: &held,
+        };
+
+        let mut players = players();
+        let (user, target) = (TeamIndex(1, 0), TeamIndex(2, 0));
+        let before = hp(&players, &target);
+
+        let results = engine
+            .execute_move(
+                &mut (),
+                &mut StepRng::new(0, 0),
+                &mut BattleData::default(),
+                effects,
+                &m,
+                &user,
+                vec![target],
+                &mut players,
+            )
+            .unwrap();
+
+        assert!(matches!(
+            results.as_slice(),
+            [Indexed(pokemon, PublicAction::SetHP(..))] if pokemon == &target
+        ));
+        assert_eq!(hp(&players, &target), before - 10);
+
+        let missing = BattleMove {
+            id: "ember".parse().unwrap(),
+            ..m
+        };
+
+        assert!(matches!(
+            engine.execute_move(
+                &mut (),
+                &mut StepRng::new(0, 0),
+                &mut BattleData::default(),
+                effects,
+                &missing,
+                &user,
+                vec![target],
+                &mut players,
+            ),
+            Err(NativeScriptError::MissingMove(..))
+        ));
+    }
+
+    #[test]
+    fn items() {
+        let id = "potion".parse().unwrap();
+
+        let mut engine = NativeScriptingEngine::new();
+        engine.register_item(id, |context| {
+            vec![Indexed(context.target.0.clone(), ActionResult::Heal(20))]
+        });
+
+        let field = Field::default();
+        let (abilities, held) = (Default::default(), Default::default());
+        let effects = Effects {
+            field: &field,
+            abilities: &abilities,
+            held: &held,
+        };
+
+        let mut players = players();
+        let target = TeamIndex(1, 0);
+        let max = hp(&players, &target);
+
+        if let Some(pokemon) = players.get_mut(&1).and_then(|p| p.party.active_mut(0)) {
+            pokemon.hp = 1;
+        }
+
+        let results = engine
+            .execute_item(
+                &mut (),
+                &mut StepRng::new(0, 0),
+                &mut BattleData::default(),
+                effects,
+                &id,
+                &1,
+                target,
+                &mut players,
+            )
+            .unwrap();
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(hp(&players, &target), 21.min(max));
+    }
+
+    #[test]
+    fn triggers() {
+        let ability = TriggerSource::Ability("regenerator".parse().unwrap());
+
+        let mut engine = NativeScriptingEngine::new();
+        engine.register_trigger(ability, Trigger::TurnEnd, |context| {
+            vec![Indexed(context.pokemon.0.clone(), ActionResult::Heal(10))]
+        });
+
+        let field = Field::default();
+        let (abilities, held) = (Default::default(), Default::default());
+        let effects = Effects {
+            field: &field,
+            abilities: &abilities,
+            held: &held,
+        };
+
+        let mut players = players();
+        let pokemon = TeamIndex(1, 0);
+
+        if let Some(pokemon) = players.get_mut(&1).and_then(|p| p.party.active_mut(0)) {
+            pokemon.hp = 1;
+        }
+
+        let mut run = |trigger| {
+            engine
+                .execute_trigger(
+                    &mut (),
+                    &mut StepRng::new(0, 0),
+                    &mut BattleData::default(),
+                    effects,
+                    trigger,
+                    &ability,
+                    &pokemon,
+                    0,
+                    None,
+                    &mut players,
+                )
+                .unwrap()
+        };
+
+        // only the events a function is registered for run
+        assert!(run(Trigger::SwitchIn).is_empty());
+        assert_eq!(run(Trigger::TurnEnd).len(), 1);
+        assert_eq!(hp(&players, &pokemon), 11);
+    }
+}
@@ -0,0 +1,39 @@
+use battle::{
+    engine::{ActionResult, PlayerQuery},
+    pokemon::{Indexed, TeamIndex},
+};
+
+use super::ScriptActionResult;
+
+/// Gives each team of a battle a slot that scripts refer to it by,
+/// so script values do not depend on the host's ID type.
+#[derive(Debug, Clone)]
+pub struct ScriptTeams<ID>(Vec<ID>);
+
+impl<ID: PartialEq + Clone> ScriptTeams<ID> {
+    pub fn new(ids: impl IntoIterator<Item = ID>) -> Self {
+        Self(ids.into_iter().collect())
+    }
+
+    pub fn of<T>(players: &PlayerQuery<ID, T>) -> Self {
+        Self::new(players.iter().map(|p| p.id().clone()))
+    }
+
+    pub fn slot(&self, position: &TeamIndex<ID>) -> Option<TeamIndex<usize>> {
+        self.0
+            .iter()
+            .position(|id| id == position.team())
+            .map(|slot| TeamIndex(slot, position.index()))
+    }
+
+    pub fn position(&self, slot: &TeamIndex<usize>) -> Option<TeamIndex<ID>> {
+        self.0
+            .get(*slot.team())
+            .map(|id| TeamIndex(id.clone(), slot.index()))
+    }
+
+    pub fn result(&self, result: ScriptActionResult) -> Option<Indexed<ID, ActionResult>> {
+        let Indexed(slot, action) = result.0;
+        self.position(&slot).map(|position| Indexed(position, action))
+    }
+}
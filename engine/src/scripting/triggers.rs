@@ -0,0 +1,64 @@
+use core::fmt::{Display, Formatter, Result as FmtResult};
+
+use battle::{
+    pokedex::item::ItemId,
+    pokemon::{AbilityId, BattlePokemon},
+};
+
+/// Events passive effects can react to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Trigger {
+    /// The pokemon entered the field.
+    SwitchIn,
+    /// The pokemon is about to use a move.
+    BeforeMove,
+    /// The pokemon was damaged by a move.
+    DamageTaken,
+    /// The pokemon was hit by a move that makes contact.
+    Contact,
+    TurnEnd,
+    Faint,
+}
+
+/// Where a passive effect comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TriggerSource {
+    Ability(AbilityId),
+    /// A held item
+    Item(ItemId),
+}
+
+impl Trigger {
+    /// Name of the script function run for this event.
+    pub const fn function(&self) -> &'static str {
+        match self {
+            Self::SwitchIn => "on_switch_in",
+            Self::BeforeMove => "on_before_move",
+            Self::DamageTaken => "on_damage_taken",
+            Self::Contact => "on_contact",
+            Self::TurnEnd => "on_turn_end",
+            Self::Faint => "on_faint",
+        }
+    }
+}
+
+impl TriggerSource {
+    /// Every source of passive effects a pokemon has.
+    pub fn of(pokemon: &BattlePokemon) -> Vec<Self> {
+        pokemon
+            .ability
+            .map(Self::Ability)
+            .into_iter()
+            .chain(pokemon.item.as_ref().map(|item| Self::Item(item.id)))
+            .collect()
+    }
+}
+
+impl Display for TriggerSource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::Ability(id) => write!(f, "ability {}", id),
+            Self::Item(id) => write!(f, "item {}", id.as_str()),
+        }
+    }
+}
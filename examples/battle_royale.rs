@@ -159,6 +159,7 @@ fn main() {
             id: id as _,
             name: Some(format!("Player {}", id)),
             party: party.clone(),
+            abilities: Default::default(),
             bag: Default::default(),
             trainer: Some(()),
             settings: PlayerSettings { gains_exp: false },
@@ -183,6 +184,8 @@ fn main() {
                 crit_rate: 0,
             },
             usage: MoveExecution::Actions(vec![MoveUse::Damage(DamageKind::Power(50))]),
+            turns: None,
+            reaches: Vec::new(),
         },
     );
 
@@ -201,6 +204,8 @@ fn main() {
                 crit_rate: 2,
             },
             usage: MoveExecution::Actions(vec![MoveUse::Damage(DamageKind::Power(90))]),
+            turns: None,
+            reaches: Vec::new(),
         },
     );
 
@@ -219,6 +224,8 @@ fn main() {
                 crit_rate: 0,
             },
             usage: MoveExecution::Script,
+            turns: None,
+            reaches: Vec::new(),
         },
     );
 
@@ -237,6 +244,8 @@ fn main() {
                 crit_rate: 0,
             },
             usage: MoveExecution::Script,
+            turns: None,
+            reaches: Vec::new(),
         },
     );
 
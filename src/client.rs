@@ -0,0 +1,633 @@
+//! Client side battle state
+
+use alloc::collections::VecDeque;
+use core::hash::Hash;
+
+use hashbrown::HashMap;
+
+use pokedex::{
+    item::{bag::SavedBag, Item, ItemId},
+    moves::{Move, MoveId},
+    pokemon::{owned::OwnedPokemon, Health, Pokemon},
+    Dex,
+};
+
+use crate::{
+    data::{BattleData, BattleWeather, FieldEvent, FieldId, WeatherEvent},
+    endpoint::{BattleEndpoint, ConnectionError},
+    message::{ClientMessage, ServerMessage},
+    party::{PlayerParty, RemoteParty},
+    player::RemovalReason,
+    pokemon::{stat::StatStages, ActivePosition, Indexed, PartyPosition, PokemonView, TeamIndex},
+    select::*,
+};
+
+pub type LocalParty<ID, T> = PlayerParty<ID, usize, OwnedPokemon, T>;
+
+/// Keeps a mirror of a battle from the point of view of a single player.
+pub struct BattleClient<ID, T, E> {
+    data: Option<BattleData>,
+    local: Option<LocalParty<ID, T>>,
+    bag: SavedBag,
+    remotes: HashMap<ID, RemoteParty<ID, T>>,
+    /// Stat stages of active pokemon, cleared when they leave the field.
+    stages: HashMap<TeamIndex<ID>, StatStages>,
+    /// Field conditions in effect
+    field: Vec<FieldId>,
+    events: VecDeque<ClientEvent<ID>>,
+    ended: bool,
+    endpoint: E,
+}
+
+/// Something that happened in the battle that a frontend may want to show.
+#[derive(Debug, Clone)]
+pub enum ClientEvent<ID> {
+    /// Local player data has been received.
+    Start,
+    AddOpponent(ID),
+    /// The host wants a selection for an active position.
+    Select(ActivePosition, Option<SelectReason>),
+    Confirm(ActivePosition, SelectConfirm),
+    Deny(ActivePosition),
+    /// Turn number, turn user, turn type
+    Announce(usize, Option<TeamIndex<ID>>, ClientActionType<ID>),
+    /// An action that has already been applied to the mirrored state.
+    Action(Indexed<ID, PublicAction>),
+    /// A pokemon was left at 0 HP.
+    Faint(TeamIndex<ID>),
+    /// The weather changed without a pokemon causing it.
+    Weather(WeatherEvent),
+    /// A field condition ended on its own.
+    Field(FieldEvent),
+    /// An error the host reported while running a turn.
+    Error(String),
+    Replace(TeamIndex<ID>, PartyPosition),
+    Reveal(TeamIndex<ID>),
+    Remove(ID, RemovalReason),
+    End(Option<ID>),
+}
+
+#[derive(Debug)]
+pub enum ClientError {
+    Connection(ConnectionError),
+    /// A pokemon sent by the host could not be found in the dex.
+    MissingDex,
+    /// Battle messages were received before the local player data.
+    NoPlayerData,
+}
+
+impl<ID: Clone + Eq + Hash, T, E: BattleEndpoint<ClientMessage<ID>, ServerMessage<ID, T>>>
+    BattleClient<ID, T, E>
+{
+    pub fn new(endpoint: E) -> Self {
+        Self {
+            data: None,
+            local: None,
+            bag: Default::default(),
+            remotes: Default::default(),
+            stages: Default::default(),
+            field: Default::default(),
+            events: Default::default(),
+            ended: false,
+            endpoint,
+        }
+    }
+
+    pub fn endpoint(&self) -> &E {
+        &self.endpoint
+    }
+
+    pub fn data(&self) -> Option<&BattleData> {
+        self.data.as_ref()
+    }
+
+    pub fn field(&self) -> &[FieldId] {
+        &self.field
+    }
+
+    pub fn local(&self) -> Option<&LocalParty<ID, T>> {
+        self.local.as_ref()
+    }
+
+    pub fn bag(&self) -> &SavedBag {
+        &self.bag
+    }
+
+    pub fn remotes(&self) -> &HashMap<ID, RemoteParty<ID, T>> {
+        &self.remotes
+    }
+
+    pub fn remote(&self, id: &ID) -> Option<&RemoteParty<ID, T>> {
+        self.remotes.get(id)
+    }
+
+    pub fn stages(&self, pokemon: &TeamIndex<ID>) -> Option<&StatStages> {
+        self.stages.get(pokemon)
+    }
+
+    /// If the client has received its data and the battle has not ended for it.
+    pub fn running(&self) -> bool {
+        self.local.is_some() && !self.ended
+    }
+
+    pub fn is_local(&self, id: &ID) -> bool {
+        self.local.as_ref().map(|l| &l.id == id).unwrap_or_default()
+    }
+
+    /// Take the events that happened since the last call.
+    pub fn events(&mut self) -> impl Iterator<Item = ClientEvent<ID>> + '_ {
+        self.events.drain(..)
+    }
+
+    pub fn select(
+        &self,
+        active: ActivePosition,
+        selection: BattleSelection<ID>,
+    ) -> Result<(), ClientError> {
+        self.send(ClientMessage::Select(active, selection))
+    }
+
+    pub fn select_move(
+        &self,
+        active: ActivePosition,
+        id: MoveId,
+        target: Option<TeamIndex<ID>>,
+    ) -> Result<(), ClientError> {
+        self.select(active, BattleSelection::Move(id, target))
+    }
+
+    pub fn select_pokemon(
+        &self,
+        active: ActivePosition,
+        pokemon: PartyPosition,
+    ) -> Result<(), ClientError> {
+        self.select(active, BattleSelection::Pokemon(pokemon))
+    }
+
+    pub fn select_item(
+        &self,
+        active: ActivePosition,
+        target: TeamIndex<ID>,
+        item: ItemId,
+    ) -> Result<(), ClientError> {
+        self.select(active, BattleSelection::Item(Indexed(target, item)))
+    }
+
+    pub fn forfeit(&self) -> Result<(), ClientError> {
+        self.send(ClientMessage::TryForfeit)
+    }
+
+    pub fn learn_move(
+        &self,
+        pokemon: PartyPosition,
+        id: MoveId,
+        index: Option<usize>,
+    ) -> Result<(), ClientError> {
+        self.send(ClientMessage::LearnMove(pokemon, id, index))
+    }
+
+    fn send(&self, message: ClientMessage<ID>) -> Result<(), ClientError> {
+        self.endpoint.send(message).map_err(ClientError::Connection)
+    }
+
+    /// Receive and apply every message waiting in the endpoint.
+    pub fn update(
+        &mut self,
+        pokedex: &Dex<Pokemon>,
+        movedex: &Dex<Move>,
+        itemdex: &Dex<Item>,
+    ) -> Result<(), ClientError> {
+        loop {
+            match self.endpoint.receive() {
+                Ok(Some(message)) => self.on_message(message, pokedex, movedex, itemdex)?,
+                Ok(None) => break Ok(()),
+                Err(err) => {
+                    self.ended = true;
+                    break Err(ClientError::Connection(err));
+                }
+            }
+        }
+    }
+
+    pub fn on_message(
+        &mut self,
+        message: ServerMessage<ID, T>,
+        pokedex: &Dex<Pokemon>,
+        movedex: &Dex<Move>,
+        itemdex: &Dex<Item>,
+    ) -> Result<(), ClientError> {
+        match message {
+            ServerMessage::PlayerData(data, local, bag) => {
+                let pokemon = local
+                    .pokemon
+                    .into_iter()
+                    .map(|p| p.try_init(pokedex, movedex, itemdex))
+                    .collect::<Option<_>>()
+                    .ok_or(ClientError::MissingDex)?;
+                self.local = Some(PlayerParty {
+                    id: local.id,
+                    name: local.name,
+                    active: local.active,
+                    pokemon,
+                    trainer: local.trainer,
+                    side: local.side,
+                });
+                self.data = Some(data);
+                self.bag = bag;
+                self.ended = false;
+                self.events.push_back(ClientEvent::Start);
+            }
+            ServerMessage::AddOpponent(remote) => {
+                self.events
+                    .push_back(ClientEvent::AddOpponent(remote.id.clone()));
+                self.remotes.insert(remote.id.clone(), remote);
+            }
+            ServerMessage::Select(active, select) => match select {
+                SelectMessage::Request(reason) => {
+                    self.events.push_back(ClientEvent::Select(active, reason))
+                }
+                SelectMessage::Confirm(confirm) => {
+                    if let SelectConfirm::Move(id, pp) = confirm {
+                        if let Some(m) = self
+                            .local
+                            .as_mut()
+                            .and_then(|local| local.active_mut(active))
+                            .and_then(|pokemon| pokemon.moves.iter_mut().find(|m| m.id() == &id))
+                        {
+                            m.pp = m.pp.saturating_sub(pp);
+                        }
+                    }
+                    self.events.push_back(ClientEvent::Confirm(active, confirm));
+                }
+                SelectMessage::Deny => self.events.push_back(ClientEvent::Deny(active)),
+            },
+            ServerMessage::Results(actions) => {
+                if self.local.is_none() {
+                    return Err(ClientError::NoPlayerData);
+                }
+                let mut user = None;
+                for action in actions {
+                    match action {
+                        ClientAction::Announce(turn, new_user, kind) => {
+                            user = new_user.clone();
+                            self.events
+                                .push_back(ClientEvent::Announce(turn, new_user, kind));
+                        }
+                        ClientAction::Actions(actions) => {
+                            for action in actions {
+                                self.apply(user.as_ref(), &action);
+                                self.events.push_back(ClientEvent::Action(action));
+                            }
+                        }
+                        ClientAction::Weather(event) => {
+                            self.weather_event(event);
+                            self.events.push_back(ClientEvent::Weather(event));
+                        }
+                        ClientAction::Field(event) => {
+                            self.field_event(event);
+                            self.events.push_back(ClientEvent::Field(event));
+                        }
+                        ClientAction::Error(err) => self.events.push_back(ClientEvent::Error(err)),
+                    }
+                }
+            }
+            ServerMessage::Replace(Indexed(target, new)) => {
+                self.replace(&target, new);
+                self.events.push_back(ClientEvent::Replace(target, new));
+            }
+            ServerMessage::Reveal(Indexed(target, view)) => {
+                if let PokemonView::Partial(pokemon) = view {
+                    if let Some(remote) = self.remotes.get_mut(target.team()) {
+                        remote.add(target.index(), Some(pokemon));
+                    }
+                }
+                self.events.push_back(ClientEvent::Reveal(target));
+            }
+            ServerMessage::Remove(id, reason, ..) => {
+                if self.is_local(&id) {
+                    self.ended = true;
+                }
+                self.remotes.remove(&id);
+                self.stages.retain(|pokemon, _| pokemon.team() != &id);
+                self.events.push_back(ClientEvent::Remove(id, reason));
+            }
+            ServerMessage::End(winner) => {
+                self.ended = true;
+                self.events.push_back(ClientEvent::End(winner));
+            }
+        }
+        Ok(())
+    }
+
+    fn replace(&mut self, target: &TeamIndex<ID>, new: PartyPosition) {
+        self.stages.remove(target);
+        match self.local.as_mut() {
+            Some(local) if &local.id == target.team() => local.replace(target.index(), Some(new)),
+            _ => {
+                if let Some(remote) = self.remotes.get_mut(target.team()) {
+                    remote.replace(target.index(), Some(new));
+                }
+            }
+        }
+    }
+
+    fn apply(
+        &mut self,
+        user: Option<&TeamIndex<ID>>,
+        Indexed(target, action): &Indexed<ID, PublicAction>,
+    ) {
+        match action {
+            PublicAction::SetHP(hp) => {
+                let hp = hp.damage();
+                let fainted = match self.local.as_mut() {
+                    Some(local) if &local.id == target.team() => {
+                        match local.active_mut(target.index()) {
+                            Some(pokemon) => {
+                                pokemon.hp = (hp * pokemon.max_hp() as f32).ceil() as Health;
+                                pokemon.fainted()
+                            }
+                            None => false,
+                        }
+                    }
+                    _ => match self
+                        .remotes
+                        .get_mut(target.team())
+                        .and_then(|party| party.active_mut(target.index()))
+                        .and_then(Option::as_mut)
+                    {
+                        Some(pokemon) => {
+                            pokemon.hp = hp;
+                            pokemon.fainted()
+                        }
+                        None => false,
+                    },
+                };
+                if fainted {
+                    self.stages.remove(target);
+                    self.events.push_back(ClientEvent::Faint(target.clone()));
+                }
+            }
+            PublicAction::AddStat(stat, stage) => {
+                self.stages
+                    .entry(target.clone())
+                    .or_default()
+                    .change_stage(*stat, *stage);
+            }
+            PublicAction::Ailment(ailment) => match self.local.as_mut() {
+                Some(local) if &local.id == target.team() => {
+                    if let Some(pokemon) = local.active_mut(target.index()) {
+                        pokemon.ailment = *ailment;
+                    }
+                }
+                _ => {
+                    if let Some(pokemon) = self
+                        .remotes
+                        .get_mut(target.team())
+                        .and_then(|party| party.active_mut(target.index()))
+                        .and_then(Option::as_mut)
+                    {
+                        pokemon.ailment = *ailment;
+                    }
+                }
+            },
+            PublicAction::Switch(new) => {
+                if let Some(user) = user.cloned() {
+                    self.replace(&user, *new);
+                }
+            }
+            PublicAction::Remove(..) => match self.local.as_mut() {
+                Some(local) if &local.id == target.team() => local.remove_active(target.index()),
+                _ => {
+                    if let Some(remote) = self.remotes.get_mut(target.team()) {
+                        remote.remove_active(target.index());
+                    }
+                }
+            },
+            PublicAction::Weather(event) => self.weather_event(*event),
+            PublicAction::Field(event) => self.field_event(*event),
+            PublicAction::Side(event) => match self.local.as_mut() {
+                Some(local) if &local.id == target.team() => {
+                    local.side.apply(*event);
+                }
+                _ => {
+                    if let Some(remote) = self.remotes.get_mut(target.team()) {
+                        remote.side.apply(*event);
+                    }
+                }
+            },
+            PublicAction::Ability(ability) => {
+                if let Some(pokemon) = self
+                    .remotes
+                    .get_mut(target.team())
+                    .and_then(|party| party.active_mut(target.index()))
+                    .and_then(Option::as_mut)
+                {
+                    pokemon.ability = Some(*ability);
+                }
+            }
+            PublicAction::Item(item, consumed) => match self.local.as_mut() {
+                Some(local) if &local.id == target.team() => {
+                    if let Some(pokemon) = local.active_mut(target.index()).filter(|_| *consumed) {
+                        pokemon.item = None;
+                    }
+                }
+                _ => {
+                    if let Some(pokemon) = self
+                        .remotes
+                        .get_mut(target.team())
+                        .and_then(|party| party.active_mut(target.index()))
+                        .and_then(Option::as_mut)
+                    {
+                        pokemon.item = (!consumed).then(|| *item);
+                    }
+                }
+            },
+            PublicAction::Recover(..) => {
+                self.apply(user, &Indexed(target.clone(), PublicAction::Ailment(None)))
+            }
+            PublicAction::Reveal
+            | PublicAction::Cancel(..)
+            | PublicAction::Hurt(..)
+            | PublicAction::Immobile(..)
+            | PublicAction::Volatile(..)
+            | PublicAction::Hits(..)
+            | PublicAction::Charge
+            | PublicAction::Recharge
+            | PublicAction::Miss => (),
+        }
+    }
+
+    fn field_event(&mut self, event: FieldEvent) {
+        match event {
+            FieldEvent::Start(id) => {
+                if !self.field.contains(&id) {
+                    self.field.push(id);
+                }
+            }
+            FieldEvent::End(id) => self.field.retain(|f| f != &id),
+        }
+    }
+
+    /// The client does not know how many turns the weather lasts.
+    fn weather_event(&mut self, event: WeatherEvent) {
+        if let Some(data) = self.data.as_mut() {
+            match event {
+                WeatherEvent::Start(weather) => {
+                    data.weather = Some(BattleWeather::new(weather, None))
+                }
+                WeatherEvent::Continue(..) => (),
+                WeatherEvent::End(..) => data.weather = None,
+            }
+        }
+    }
+}
+
+impl From<ConnectionError> for ClientError {
+    fn from(value: ConnectionError) -> Self {
+        Self::Connection(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use pokedex::Dex;
+
+    use crate::{
+        data::{BattleData, FieldEvent, Weather, WeatherEvent},
+        endpoint::{BattleEndpoint, ConnectionError},
+        message::{ClientMessage, ServerMessage},
+        party::PlayerParty,
+        player::RemovalReason,
+        pokemon::{stat::BattleStatType, Indexed, TeamIndex},
+        select::{ClientAction, PublicAction},
+        side::{SideCondition, SideEvent},
+    };
+
+    use super::{BattleClient, ClientError, ClientEvent};
+
+    /// An endpoint that never has messages to receive.
+    struct EmptyEndpoint;
+
+    impl BattleEndpoint<ClientMessage<u8>, ServerMessage<u8, ()>> for EmptyEndpoint {
+        fn send(&self, _: ClientMessage<u8>) -> Result<(), ConnectionError> {
+            Ok(())
+        }
+
+        fn receive(&self) -> Result<Option<ServerMessage<u8, ()>>, ConnectionError> {
+            Ok(None)
+        }
+    }
+
+    fn message(
+        client: &mut BattleClient<u8, (), EmptyEndpoint>,
+        message: ServerMessage<u8, ()>,
+    ) -> Result<(), ClientError> {
+        client.on_message(message, &Dex::default(), &Dex::default(), &Dex::default())
+    }
+
+    #[test]
+    fn server_messages() {
+        let mut client = BattleClient::new(EmptyEndpoint);
+
+        assert!(matches!(
+            message(&mut client, ServerMessage::Results(Vec::new())),
+            Err(ClientError::NoPlayerData)
+        ));
+
+        message(
+            &mut client,
+            ServerMessage::PlayerData(
+                BattleData::default(),
+                PlayerParty {
+                    id: 0,
+                    name: None,
+                    active: vec![],
+                    pokemon: Default::default(),
+                    trainer: None,
+                    side: Default::default(),
+                },
+                Default::default(),
+            ),
+        )
+        .unwrap();
+        assert!(client.running());
+        assert!(client.is_local(&0));
+
+        message(
+            &mut client,
+            ServerMessage::AddOpponent(PlayerParty {
+                id: 1,
+                name: None,
+                active: vec![None],
+                pokemon: Default::default(),
+                trainer: None,
+                side: Default::default(),
+            }),
+        )
+        .unwrap();
+        assert!(client.remote(&1).is_some());
+
+        let opponent = TeamIndex(1, 0);
+        let field = "gravity".parse().unwrap();
+
+        message(
+            &mut client,
+            ServerMessage::Results(vec![
+                ClientAction::Actions(vec![
+                    Indexed(opponent, PublicAction::AddStat(BattleStatType::Accuracy, 1)),
+                    Indexed(
+                        opponent,
+                        PublicAction::Side(SideEvent::Start(SideCondition::Reflect)),
+                    ),
+                    Indexed(opponent, PublicAction::Field(FieldEvent::Start(field))),
+                ]),
+                ClientAction::Weather(WeatherEvent::Start(Weather::Hail)),
+            ]),
+        )
+        .unwrap();
+        assert_eq!(
+            client
+                .stages(&opponent)
+                .map(|s| s[BattleStatType::Accuracy]),
+            Some(1)
+        );
+        assert!(client
+            .remote(&1)
+            .map(|r| r.side.has(SideCondition::Reflect))
+            .unwrap_or_default());
+        assert_eq!(client.field(), &[field]);
+        assert_eq!(
+            client.data().and_then(BattleData::weather),
+            Some(Weather::Hail)
+        );
+
+        message(
+            &mut client,
+            ServerMessage::Results(vec![
+                ClientAction::Field(FieldEvent::End(field)),
+                ClientAction::Weather(WeatherEvent::End(Weather::Hail)),
+            ]),
+        )
+        .unwrap();
+        assert!(client.field().is_empty());
+        assert_eq!(client.data().and_then(BattleData::weather), None);
+
+        message(
+            &mut client,
+            ServerMessage::Remove(1, RemovalReason::Loss, 0),
+        )
+        .unwrap();
+        assert!(client.remote(&1).is_none());
+        assert!(client.stages(&opponent).is_none());
+        assert!(client.running());
+
+        message(&mut client, ServerMessage::End(Some(0))).unwrap();
+        assert!(!client.running());
+
+        let events = client.events().collect::<Vec<_>>();
+        assert!(matches!(events.first(), Some(ClientEvent::Start)));
+        assert!(matches!(events.last(), Some(ClientEvent::End(Some(0)))));
+        assert_eq!(client.events().count(), 0);
+    }
+}
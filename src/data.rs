@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+use pokedex::types::PokemonType;
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct BattleData {
     /// constant
@@ -9,7 +11,9 @@ pub struct BattleData {
     /// constant
     #[serde(default)]
     pub settings: BattleSettings,
-    // add weather, etc
+    /// Starts as the weather of the overworld
+    #[serde(default)]
+    pub weather: Option<BattleWeather>,
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
@@ -25,6 +29,96 @@ pub struct BattleSettings {
     pub allow_forfeit: bool,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Weather {
+    Rain,
+    Sun,
+    Sandstorm,
+    Hail,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BattleWeather {
+    pub weather: Weather,
+    /// Turns left, or [None] if the weather does not end on its own.
+    #[serde(default)]
+    pub turns: Option<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WeatherEvent {
+    Start(Weather),
+    Continue(Weather),
+    End(Weather),
+}
+
+/// Id of a field condition, such as a terrain or Trick Room.
+pub type FieldId = tinystr::TinyAsciiStr<16>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FieldEvent {
+    Start(FieldId),
+    End(FieldId),
+}
+
+impl Weather {
+    /// How much this weather multiplies the damage of a move of a type.
+    pub fn damage(&self, move_type: PokemonType) -> f64 {
+        match (self, move_type) {
+            (Self::Rain, PokemonType::Water) | (Self::Sun, PokemonType::Fire) => 1.5,
+            (Self::Rain, PokemonType::Fire) | (Self::Sun, PokemonType::Water) => 0.5,
+            _ => 1.0,
+        }
+    }
+
+    /// If a pokemon of this type does not take damage from this weather at the end of a turn.
+    pub fn immune(&self, pokemon_type: PokemonType) -> bool {
+        match self {
+            Self::Sandstorm => matches!(
+                pokemon_type,
+                PokemonType::Rock | PokemonType::Ground | PokemonType::Steel
+            ),
+            Self::Hail => pokemon_type == PokemonType::Ice,
+            Self::Rain | Self::Sun => true,
+        }
+    }
+}
+
+impl BattleWeather {
+    pub fn new(weather: Weather, turns: Option<u8>) -> Self {
+        Self { weather, turns }
+    }
+
+    /// Count down a turn, returning false once the weather is over.
+    pub fn tick(&mut self) -> bool {
+        match self.turns.as_mut() {
+            Some(turns) => {
+                *turns = turns.saturating_sub(1);
+                *turns != 0
+            }
+            None => true,
+        }
+    }
+}
+
+impl WeatherEvent {
+    /// The event for weather that lasted through a turn, or changed in it.
+    pub fn turn(before: Option<Weather>, after: Option<Weather>) -> Option<Self> {
+        match (before, after) {
+            (Some(before), Some(after)) if before == after => Some(Self::Continue(after)),
+            (.., Some(after)) => Some(Self::Start(after)),
+            (Some(before), None) => Some(Self::End(before)),
+            (None, None) => None,
+        }
+    }
+}
+
+impl BattleData {
+    pub fn weather(&self) -> Option<Weather> {
+        self.weather.map(|w| w.weather)
+    }
+}
+
 impl VersusType {
     pub fn is_wild(&self) -> bool {
         matches!(self, Self::Wild)
@@ -41,6 +135,7 @@ impl Default for BattleData {
             versus: VersusType::Trainer,
             active: 1,
             settings: Default::default(),
+            weather: None,
         }
     }
 }
@@ -56,3 +151,67 @@ impl Default for BattleSettings {
 const fn const_true() -> bool {
     true
 }
+
+#[cfg(test)]
+mod tests {
+
+    use pokedex::types::PokemonType;
+
+    use super::{BattleWeather, Weather, WeatherEvent};
+
+    #[test]
+    fn weather_tick() {
+        let mut weather = BattleWeather::new(Weather::Rain, Some(2));
+        assert!(weather.tick());
+        assert!(!weather.tick());
+        assert!(
+            !weather.tick(),
+            "Weather started lasting again after ending!"
+        );
+
+        let mut weather = BattleWeather::new(Weather::Sun, None);
+        for _ in 0..u8::MAX {
+            assert!(weather.tick(), "Weather without a turn count ended!");
+        }
+    }
+
+    #[test]
+    fn weather_damage() {
+        assert_eq!(Weather::Rain.damage(PokemonType::Water), 1.5);
+        assert_eq!(Weather::Rain.damage(PokemonType::Fire), 0.5);
+        assert_eq!(Weather::Sun.damage(PokemonType::Fire), 1.5);
+        assert_eq!(Weather::Sun.damage(PokemonType::Water), 0.5);
+        assert_eq!(Weather::Sandstorm.damage(PokemonType::Rock), 1.0);
+        assert_eq!(Weather::Hail.damage(PokemonType::Water), 1.0);
+    }
+
+    #[test]
+    fn weather_immune() {
+        assert!(Weather::Sandstorm.immune(PokemonType::Steel));
+        assert!(!Weather::Sandstorm.immune(PokemonType::Ice));
+        assert!(Weather::Hail.immune(PokemonType::Ice));
+        assert!(!Weather::Hail.immune(PokemonType::Rock));
+        assert!(Weather::Rain.immune(PokemonType::Fire));
+    }
+
+    #[test]
+    fn weather_event() {
+        assert_eq!(
+            WeatherEvent::turn(Some(Weather::Rain), Some(Weather::Rain)),
+            Some(WeatherEvent::Continue(Weather::Rain))
+        );
+        assert_eq!(
+            WeatherEvent::turn(Some(Weather::Rain), Some(Weather::Hail)),
+            Some(WeatherEvent::Start(Weather::Hail))
+        );
+        assert_eq!(
+            WeatherEvent::turn(None, Some(Weather::Sun)),
+            Some(WeatherEvent::Start(Weather::Sun))
+        );
+        assert_eq!(
+            WeatherEvent::turn(Some(Weather::Sandstorm), None),
+            Some(WeatherEvent::End(Weather::Sandstorm))
+        );
+        assert_eq!(WeatherEvent::turn(None, None), None);
+    }
+}
@@ -8,8 +8,13 @@ pub trait BattleEndpoint<A, B> {
 #[derive(Debug)]
 pub enum ConnectionError {
     Disconnected,
+    /// Too many messages in a row were dropped by a middleware endpoint
+    Flooded,
 }
 
+mod middleware;
+pub use middleware::*;
+
 #[cfg(feature = "mpsc_endpoint")]
 pub use mpsc::*;
 
@@ -0,0 +1,293 @@
+//! Wrappers around a [BattleEndpoint] that can be stacked on top of each other.
+
+use core::{
+    fmt::Debug,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::message::{ClientMessage, ServerMessage};
+
+use super::{BattleEndpoint, ConnectionError};
+
+/// Maximum number of messages a wrapper will throw away in a single call to receive
+/// before giving up with [ConnectionError::Flooded].
+pub const MAX_DROPPED: usize = 16;
+
+/// Logs every message sent and received through the inner endpoint.
+pub struct LogEndpoint<E> {
+    pub inner: E,
+    name: String,
+    start: Instant,
+}
+
+impl<E> LogEndpoint<E> {
+    pub fn new(name: impl Into<String>, inner: E) -> Self {
+        Self {
+            inner,
+            name: name.into(),
+            start: Instant::now(),
+        }
+    }
+
+    /// Seconds since this endpoint was created.
+    fn timestamp(&self) -> f32 {
+        self.start.elapsed().as_secs_f32()
+    }
+}
+
+impl<A: Debug, B: Debug, E: BattleEndpoint<A, B>> BattleEndpoint<A, B> for LogEndpoint<E> {
+    fn send(&self, message: A) -> Result<(), ConnectionError> {
+        log::debug!("[{:.3}] {} <- {:?}", self.timestamp(), self.name, message);
+        self.inner.send(message)
+    }
+
+    fn receive(&self) -> Result<Option<B>, ConnectionError> {
+        match self.inner.receive() {
+            Ok(Some(message)) => {
+                log::debug!("[{:.3}] {} -> {:?}", self.timestamp(), self.name, message);
+                Ok(Some(message))
+            }
+            Ok(None) => Ok(None),
+            Err(err) => {
+                log::warn!("[{:.3}] {} x {:?}", self.timestamp(), self.name, err);
+                Err(err)
+            }
+        }
+    }
+}
+
+/// Drops [ClientMessage::Select] messages that arrive faster than the given rate.
+pub struct SelectLimitEndpoint<E> {
+    pub inner: E,
+    /// Selections allowed per window
+    limit: usize,
+    window: Duration,
+    /// Start of the current window and selections received during it
+    current: Mutex<(Instant, usize)>,
+    dropped: AtomicUsize,
+}
+
+impl<E> SelectLimitEndpoint<E> {
+    pub fn new(inner: E, limit: usize, window: Duration) -> Self {
+        Self {
+            inner,
+            limit,
+            window,
+            current: Mutex::new((Instant::now(), 0)),
+            dropped: Default::default(),
+        }
+    }
+
+    /// Number of selections that have been dropped so far.
+    pub fn dropped(&self) -> usize {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    fn allow(&self) -> bool {
+        let mut current = self.current.lock().unwrap_or_else(|err| err.into_inner());
+        let (start, count) = &mut *current;
+        if start.elapsed() >= self.window {
+            *start = Instant::now();
+            *count = 0;
+        }
+        match *count < self.limit {
+            true => {
+                *count += 1;
+                true
+            }
+            false => {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+                false
+            }
+        }
+    }
+}
+
+impl<ID, T, E: BattleEndpoint<ServerMessage<ID, T>, ClientMessage<ID>>>
+    BattleEndpoint<ServerMessage<ID, T>, ClientMessage<ID>> for SelectLimitEndpoint<E>
+{
+    fn send(&self, message: ServerMessage<ID, T>) -> Result<(), ConnectionError> {
+        self.inner.send(message)
+    }
+
+    fn receive(&self) -> Result<Option<ClientMessage<ID>>, ConnectionError> {
+        for _ in 0..MAX_DROPPED {
+            match self.inner.receive()? {
+                Some(ClientMessage::Select(..)) if !self.allow() => continue,
+                message => return Ok(message),
+            }
+        }
+        Err(ConnectionError::Flooded)
+    }
+}
+
+/// Drops received messages that do not pass a validation function.
+pub struct ValidateEndpoint<E, F> {
+    pub inner: E,
+    validate: F,
+    rejected: AtomicUsize,
+}
+
+impl<E, F> ValidateEndpoint<E, F> {
+    pub fn new(inner: E, validate: F) -> Self {
+        Self {
+            inner,
+            validate,
+            rejected: Default::default(),
+        }
+    }
+
+    /// Number of messages that have been rejected so far.
+    pub fn rejected(&self) -> usize {
+        self.rejected.load(Ordering::Relaxed)
+    }
+
+    /// Also reject messages whose size, as measured by `size`, is over `max`.
+    /// The size is usually the length of the serialized message.
+    pub fn max_size<B>(
+        self,
+        max: usize,
+        size: impl Fn(&B) -> usize,
+    ) -> ValidateEndpoint<E, impl Fn(&B) -> bool>
+    where
+        F: Fn(&B) -> bool,
+    {
+        let validate = self.validate;
+        ValidateEndpoint {
+            inner: self.inner,
+            validate: move |message: &B| size(message) <= max && validate(message),
+            rejected: self.rejected,
+        }
+    }
+}
+
+impl<A, B, E: BattleEndpoint<A, B>, F: Fn(&B) -> bool> BattleEndpoint<A, B>
+    for ValidateEndpoint<E, F>
+{
+    fn send(&self, message: A) -> Result<(), ConnectionError> {
+        self.inner.send(message)
+    }
+
+    fn receive(&self) -> Result<Option<B>, ConnectionError> {
+        for _ in 0..MAX_DROPPED {
+            match self.inner.receive()? {
+                Some(message) if !(self.validate)(&message) => {
+                    self.rejected.fetch_add(1, Ordering::Relaxed);
+                }
+                message => return Ok(message),
+            }
+        }
+        Err(ConnectionError::Flooded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::{collections::VecDeque, sync::Mutex, time::Duration};
+
+    use crate::{
+        endpoint::{BattleEndpoint, ConnectionError},
+        message::{ClientMessage, ServerMessage},
+        select::BattleSelection,
+    };
+
+    use super::{SelectLimitEndpoint, ValidateEndpoint, MAX_DROPPED};
+
+    /// Hands out queued messages and throws away anything sent to it.
+    struct QueueEndpoint(Mutex<VecDeque<ClientMessage<u8>>>);
+
+    impl QueueEndpoint {
+        fn new(messages: impl IntoIterator<Item = ClientMessage<u8>>) -> Self {
+            Self(Mutex::new(messages.into_iter().collect()))
+        }
+    }
+
+    impl BattleEndpoint<ServerMessage<u8, ()>, ClientMessage<u8>> for QueueEndpoint {
+        fn send(&self, _: ServerMessage<u8, ()>) -> Result<(), ConnectionError> {
+            Ok(())
+        }
+
+        fn receive(&self) -> Result<Option<ClientMessage<u8>>, ConnectionError> {
+            Ok(self.0.lock().unwrap().pop_front())
+        }
+    }
+
+    fn select() -> ClientMessage<u8> {
+        ClientMessage::Select(0, BattleSelection::Pokemon(1))
+    }
+
+    #[test]
+    fn select_limit() {
+        let endpoint = SelectLimitEndpoint::new(
+            QueueEndpoint::new([select(), select(), ClientMessage::TryForfeit]),
+            1,
+            Duration::from_secs(60),
+        );
+
+        assert_eq!(endpoint.receive().unwrap(), Some(select()));
+        assert_eq!(endpoint.receive().unwrap(), Some(ClientMessage::TryForfeit));
+        assert_eq!(endpoint.receive().unwrap(), None);
+        assert_eq!(endpoint.dropped(), 1);
+    }
+
+    #[test]
+    fn select_limit_flooded() {
+        let endpoint = SelectLimitEndpoint::new(
+            QueueEndpoint::new(core::iter::repeat_with(select).take(MAX_DROPPED + 2)),
+            1,
+            Duration::from_secs(60),
+        );
+
+        assert_eq!(endpoint.receive().unwrap(), Some(select()));
+        assert!(matches!(endpoint.receive(), Err(ConnectionError::Flooded)));
+        assert_eq!(endpoint.dropped(), MAX_DROPPED);
+        assert_eq!(endpoint.receive().unwrap(), None);
+    }
+
+    #[test]
+    fn validate() {
+        let endpoint = ValidateEndpoint::new(
+            QueueEndpoint::new([select(), ClientMessage::TryForfeit]),
+            |message: &ClientMessage<u8>| !matches!(message, ClientMessage::Select(..)),
+        );
+
+        assert_eq!(endpoint.receive().unwrap(), Some(ClientMessage::TryForfeit));
+        assert_eq!(endpoint.rejected(), 1);
+    }
+
+    #[test]
+    fn validate_flooded() {
+        let endpoint = ValidateEndpoint::new(
+            QueueEndpoint::new(core::iter::repeat_with(select).take(MAX_DROPPED)),
+            |_: &ClientMessage<u8>| false,
+        );
+
+        assert!(matches!(endpoint.receive(), Err(ConnectionError::Flooded)));
+        assert_eq!(endpoint.rejected(), MAX_DROPPED);
+    }
+
+    #[test]
+    fn max_size() {
+        let endpoint = ValidateEndpoint::new(
+            QueueEndpoint::new([
+                ClientMessage::TryForfeit,
+                ClientMessage::Select(0, BattleSelection::Pokemon(2)),
+                select(),
+            ]),
+            |message: &ClientMessage<u8>| !matches!(message, ClientMessage::TryForfeit),
+        )
+        .max_size(1, |message: &ClientMessage<u8>| match message {
+            ClientMessage::Select(_, BattleSelection::Pokemon(index)) => *index,
+            _ => 0,
+        });
+
+        // the first message fails the original validator and the second is too big
+        assert_eq!(endpoint.receive().unwrap(), Some(select()));
+        assert_eq!(endpoint.rejected(), 2);
+    }
+}
@@ -7,16 +7,23 @@ use std::error::Error;
 
 use rand::Rng;
 
-use pokedex::{ailment::LiveAilment, item::ItemId, moves::MoveId, pokemon::Health};
+use pokedex::{
+    ailment::LiveAilment,
+    item::ItemId,
+    moves::MoveId,
+    pokemon::{stat::BaseStat, Health},
+};
 
 use crate::{
-    data::BattleData,
-    moves::{BattleMove, DamageResult, MoveCancelId, RemovePokemonId},
+    data::{BattleData, BattleWeather, FieldEvent, FieldId, WeatherEvent},
+    moves::{BattleMove, ClientDamage, DamageResult, MoveCancelId, RemovePokemonId, FAILED},
     pokemon::{
         stat::{BattleStatType, Stage},
+        volatile::{VolatileEvent, VolatileStatus},
         ActivePosition, BattlePokemon, Indexed, TeamIndex,
     },
     select::{BattleSelection, PublicAction, SelectMessage},
+    side::SideEvent,
 };
 
 mod active;
@@ -61,6 +68,50 @@ pub trait BattleEngine<ID: Clone + Hash + Eq + 'static, T>: Send + Sync + 'stati
         players: &mut PlayerQuery<ID, T>,
     ) -> Result<Vec<Indexed<ID, PublicAction>>, Self::ExecutionError>;
 
+    /// run the effects of a pokemon entering the field
+    fn switch_in(
+        &self,
+        data: &mut Self::Data,
+        random: &mut (impl Rng + Clone + Send + Sync + 'static),
+        battle: &mut BattleData,
+        pokemon: &TeamIndex<ID>,
+        players: &mut PlayerQuery<ID, T>,
+    ) -> Result<Vec<Indexed<ID, PublicAction>>, Self::ExecutionError> {
+        let _ = (data, random, battle, pokemon, players);
+        Ok(Vec::new())
+    }
+
+    /// If slower pokemon should move before faster ones
+    fn slower_first(&self, data: &Self::Data) -> bool {
+        let _ = data;
+        false
+    }
+
+    /// Speed of a pokemon when the order of moves is decided
+    fn speed(&self, data: &Self::Data, pokemon: &BattlePokemon) -> BaseStat {
+        let _ = data;
+        pokemon.speed()
+    }
+
+    /// A selection the pokemon has to make this turn, such as the second turn of a charging move.
+    /// The host queues it instead of asking the player.
+    fn forced(&self, data: &Self::Data, pokemon: &TeamIndex<ID>) -> Option<BattleSelection<ID>> {
+        let _ = (data, pokemon);
+        None
+    }
+
+    /// If the forced selection of a pokemon is recharging rather than using its move again.
+    fn recharging(&self, data: &Self::Data, pokemon: &TeamIndex<ID>) -> bool {
+        let _ = (data, pokemon);
+        false
+    }
+
+    /// The field conditions in effect
+    fn field(&self, data: &Self::Data) -> Vec<FieldId> {
+        let _ = data;
+        Vec::new()
+    }
+
     fn reset(&self, data: &mut Self::Data);
 
     fn get_move(&self, id: &MoveId) -> Option<&BattleMove>;
@@ -154,22 +205,48 @@ pub enum ActionResult {
     Reveal(bool),
     Cancel(MoveCancelId),
     Remove(RemovePokemonId),
+    /// Change or clear the weather of the battle
+    Weather(Option<BattleWeather>),
+    /// Start (true) or end (false) a field condition
+    Field(FieldId, bool),
+    /// Change the side of the target's team
+    Side(SideEvent),
+    /// Badly poison a poisoned pokemon, so its poison damage grows each turn
+    Toxic,
+    /// Start (true) or end (false) a volatile status
+    Volatile(VolatileStatus, bool),
+    /// How many times the user hit with a multi-hit move
+    Hits(u8),
     Fail,
     Miss,
 }
 
 impl PublicAction {
-    pub fn new(pokemon: &BattlePokemon, result: ActionResult) -> Self {
-        match result {
-            ActionResult::Damage(_) => todo!(),
-            ActionResult::Heal(_) => todo!(),
-            ActionResult::Ailment(_) => todo!(),
-            ActionResult::Stat(_, _) => todo!(),
-            ActionResult::Reveal(_) => todo!(),
-            ActionResult::Cancel(_) => todo!(),
-            ActionResult::Remove(_) => todo!(),
-            ActionResult::Fail => todo!(),
-            ActionResult::Miss => todo!(),
-        }
+    /// The action clients see for a result, given the pokemon after the result was applied to it.
+    /// Results that do not change anything clients can see, like clearing a weather that already ended, have none.
+    pub fn new(pokemon: &BattlePokemon, result: ActionResult) -> Option<Self> {
+        Some(match result {
+            ActionResult::Damage(result) => Self::SetHP(ClientDamage::Result(DamageResult {
+                damage: pokemon.percent_hp(),
+                effective: result.effective,
+                crit: result.crit,
+            })),
+            ActionResult::Heal(..) => Self::SetHP(ClientDamage::Number(pokemon.percent_hp())),
+            ActionResult::Ailment(ailment) => Self::Ailment(ailment),
+            ActionResult::Stat(stat, stage) => Self::AddStat(stat, stage),
+            ActionResult::Reveal(..) => Self::Reveal,
+            ActionResult::Cancel(reason) => Self::Cancel(reason),
+            ActionResult::Remove(reason) => Self::Remove(reason),
+            ActionResult::Weather(weather) => Self::Weather(WeatherEvent::Start(weather?.weather)),
+            ActionResult::Field(id, true) => Self::Field(FieldEvent::Start(id)),
+            ActionResult::Field(id, false) => Self::Field(FieldEvent::End(id)),
+            ActionResult::Side(event) => Self::Side(event),
+            ActionResult::Toxic => Self::Ailment(pokemon.ailment),
+            ActionResult::Volatile(status, true) => Self::Volatile(VolatileEvent::Start(status)),
+            ActionResult::Volatile(status, false) => Self::Volatile(VolatileEvent::End(status)),
+            ActionResult::Hits(hits) => Self::Hits(hits),
+            ActionResult::Fail => Self::Cancel(FAILED),
+            ActionResult::Miss => Self::Miss,
+        })
     }
 }
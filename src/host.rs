@@ -5,7 +5,7 @@ use core::{fmt::Debug, hash::Hash};
 
 use rand::Rng;
 
-use pokedex::{moves::Move, Dex};
+use pokedex::{moves::Move, pokemon::owned::SavedPokemon, Dex};
 
 use crate::{
     data::*,
@@ -24,6 +24,8 @@ use crate::{
 #[deprecated]
 pub static test: std::sync::atomic::AtomicU8 = std::sync::atomic::AtomicU8::new(0);
 
+#[cfg(feature = "ai")]
+pub mod definition;
 pub mod moves;
 mod party;
 mod player;
@@ -32,6 +34,10 @@ mod player;
 
 pub use player::PlayerData;
 
+/// Maximum number of messages read from a single player each update,
+/// so a client flooding the host cannot stall the battle.
+pub const MESSAGES_PER_UPDATE: usize = 16;
+
 /// A battle host.
 pub struct Battle<
     ID: Debug + Clone + Ord + Hash + Send + Sync + 'static,
@@ -177,6 +183,7 @@ impl<
                     .map(|pokemon| pokemon.uninit())
                     .collect(),
                 trainer: player.party.trainer.clone(),
+                side: player.party.side.clone(),
             },
             player.bag.save(),
         )) {
@@ -204,6 +211,7 @@ impl<
                         .map(BattlePokemon::get_revealed)
                         .collect(),
                     trainer: other.party.trainer.clone(),
+                    side: other.party.side.clone(),
                 }))
             {
                 errors.push(BattleError(
@@ -221,25 +229,24 @@ impl<
         }
     }
 
-    fn send_select(&self) -> Result<(), Vec<BattleError<ID>>> {
+    fn send_select(&mut self, engine: &E) -> Result<(), Vec<BattleError<ID>>> {
         let mut errors = Vec::new();
-        for player in self.players.iter() {
+        for player in self.players.iter_mut() {
             for index in 0..player.party.active.len() {
-                if player
-                    .party
-                    .active
-                    .get(index)
-                    .map(Option::as_ref)
-                    .flatten()
-                    .is_some()
-                {
-                    if let Err(err) =
-                        player.send(ServerMessage::Select(index, SelectMessage::Request(None)))
-                    {
-                        errors.push(BattleError(
-                            player.id().clone(),
-                            BattleErrors::Connection(err),
-                        ));
+                let forced = engine.forced(&self.edata, &TeamIndex(player.id().clone(), index));
+                if let Some(active) = player.party.active.get_mut(index).and_then(Option::as_mut) {
+                    match forced {
+                        Some(selection) => active.queued_move = Some(selection),
+                        None => {
+                            if let Err(err) = player
+                                .send(ServerMessage::Select(index, SelectMessage::Request(None)))
+                            {
+                                errors.push(BattleError(
+                                    player.id().clone(),
+                                    BattleErrors::Connection(err),
+                                ));
+                            }
+                        }
                     }
                 }
             }
@@ -276,10 +283,12 @@ impl<
         for player in self.players.iter_mut() {
             moves::queue_player(
                 engine,
+                &self.edata,
                 &mut queue,
                 &player.party.id,
                 &mut player.party.active,
                 &mut player.party.pokemon,
+                &player.party.side,
                 random,
             )
         }
@@ -400,6 +409,22 @@ impl<
         }
     }
 
+    /// The party of each player as it is now, such as after the battle ends.
+    /// Items used up in battle are no longer held.
+    pub fn parties(&self) -> impl Iterator<Item = (&ID, Vec<SavedPokemon>)> + '_ {
+        self.players.unfiltered_iter().map(|player| {
+            (
+                player.id(),
+                player
+                    .party
+                    .pokemon
+                    .iter()
+                    .map(|pokemon| pokemon.p.clone().uninit())
+                    .collect(),
+            )
+        })
+    }
+
     pub fn running(&self) -> bool {
         !matches!(self.state, BattleState::Winner(..))
     }
@@ -418,7 +443,7 @@ impl<
 
         let mut errors = Vec::new();
         // self.timer.update(delta);
-        if let Err(errs) = self.process(engine, movedex) {
+        if let Err(errs) = self.process(engine, random, movedex) {
             errors.extend(errs);
         }
 
@@ -433,7 +458,7 @@ impl<
                 match wait {
                     false => {
                         *wait = true;
-                        if let Err(errs) = self.send_select() {
+                        if let Err(errs) = self.send_select(engine) {
                             errors.extend(errs);
                         }
                     }
@@ -506,14 +531,21 @@ impl<
     //     }
     // }
 
-    fn process(&mut self, engine: &E, movedex: &Dex<Move>) -> Result<(), Vec<BattleError<ID>>> {
+    fn process<R: Rng + Clone + Send + Sync + 'static>(
+        &mut self,
+        engine: &E,
+        random: &mut R,
+        movedex: &Dex<Move>,
+    ) -> Result<(), Vec<BattleError<ID>>> {
         let mut errors = Vec::new();
+        // pokemon sent out to replace fainted ones
+        let mut replaced = Vec::new();
         let mut i = 0usize;
         loop {
             if self.players.get_index(i).is_none() {
                 break;
             }
-            loop {
+            for _ in 0..MESSAGES_PER_UPDATE {
                 let player = self.players.get_index_mut(i).unwrap();
                 match player.endpoint.receive() {
                     Ok(Some(message)) => match message {
@@ -527,8 +559,12 @@ impl<
                                                     true => {
                                                         if !player.party.active_contains(new) {
                                                             let id = player.id().clone();
+                                                            if let Some(pokemon) = player.party.active_mut(active) {
+                                                                pokemon.switch_out();
+                                                            }
                                                             player.party.active[active] =
                                                                 Some(new.into());
+                                                            replaced.push(TeamIndex(id.clone(), active));
                                                             let unknown =
                                                                 player.party.reveal_and_get(new);
     
@@ -665,12 +701,35 @@ impl<
                             player.id().clone(),
                             BattleErrors::Connection(err),
                         ));
+                        break;
                     }
                 }
             }
             i += 1;
         }
 
+        for pokemon in replaced {
+            let action = match engine.switch_in(
+                &mut self.edata,
+                random,
+                &mut self.data,
+                &pokemon,
+                &mut self.players,
+            ) {
+                Ok(actions) if actions.is_empty() => continue,
+                Ok(actions) => ClientAction::Actions(actions),
+                Err(err) => ClientAction::Error(err.to_string()),
+            };
+            for player in self.players.unfiltered_iter().filter(|p| p.is_ready()) {
+                if let Err(err) = player.send(ServerMessage::Results(vec![action.clone()])) {
+                    errors.push(BattleError(
+                        player.id().clone(),
+                        BattleErrors::Connection(err),
+                    ));
+                }
+            }
+        }
+
         match errors.is_empty() {
             true => Ok(()),
             false => Err(errors),
@@ -690,11 +749,12 @@ impl<
         for Indexed(user_id, bmove) in queue {
             match bmove {
                 BattleSelection::Move(used_move, target) => {
-                    player_queue.push(ClientAction::Announce(
-                        index,
-                        Some(user_id.clone()),
-                        ClientActionType::Move(used_move),
-                    ));
+                    let kind = match engine.recharging(&self.edata, &user_id) {
+                        true => ClientActionType::Recharge,
+                        false => ClientActionType::Move(used_move),
+                    };
+
+                    player_queue.push(ClientAction::Announce(index, Some(user_id.clone()), kind));
 
                     if self
                         .players
@@ -761,6 +821,10 @@ impl<
                         .find(|p| &p.party.id == user_id.team())
                     {
                         Some(user) => {
+                            if let Some(pokemon) = user.party.active_mut(user_id.index()) {
+                                pokemon.switch_out();
+                            }
+
                             user.party.replace(user_id.index(), Some(new));
 
                             if let Some(unknown) = user
@@ -781,22 +845,241 @@ impl<
                     }
 
                     player_queue.push(ClientAction::Actions(vec![Indexed(
-                        user_id,
+                        user_id.clone(),
                         PublicAction::Switch(new),
                     )]));
+
+                    match engine.switch_in(
+                        &mut self.edata,
+                        random,
+                        &mut self.data,
+                        &user_id,
+                        &mut self.players,
+                    ) {
+                        Ok(actions) if actions.is_empty() => (),
+                        Ok(actions) => player_queue.push(ClientAction::Actions(actions)),
+                        Err(err) => player_queue.push(ClientAction::Error(err.to_string())),
+                    }
                 }
             }
             index += 1;
         }
 
         player_queue.push(ClientAction::Announce(index, None, ClientActionType::Post));
-        player_queue.push(
-            match engine.post(&mut self.edata, random, &mut self.data, &mut self.players) {
-                Ok(post) => ClientAction::Actions(post),
-                Err(err) => ClientAction::Error(err.to_string()),
-            },
-        );
+
+        let weather = self.data.weather();
+        let field = engine.field(&self.edata);
+
+        let post = match engine.post(&mut self.edata, random, &mut self.data, &mut self.players) {
+            Ok(post) => ClientAction::Actions(post),
+            Err(err) => ClientAction::Error(err.to_string()),
+        };
+
+        if let Some(event) = WeatherEvent::turn(weather, self.data.weather()) {
+            player_queue.push(ClientAction::Weather(event));
+        }
+
+        let ended: Vec<FieldId> = match &post {
+            ClientAction::Actions(actions) => actions
+                .iter()
+                .flat_map(|Indexed(.., action)| match action {
+                    PublicAction::Field(FieldEvent::End(id)) => Some(*id),
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        player_queue.push(post);
+
+        let remaining = engine.field(&self.edata);
+
+        for id in field {
+            if !remaining.contains(&id) && !ended.contains(&id) {
+                player_queue.push(ClientAction::Field(FieldEvent::End(id)));
+            }
+        }
 
         player_queue
     }
 }
+
+#[cfg(test)]
+mod tests {
+
+    use core::convert::Infallible;
+    use std::sync::Arc;
+
+    use pokedex::{
+        moves::{set::OwnedMoveSet, MoveId},
+        pokemon::{
+            data::{Breeding, Gender, GrowthRate, Training},
+            owned::OwnedPokemon,
+            stat::StatSet,
+            Nature, Pokemon, PokemonId,
+        },
+        types::{PokemonType, PokemonTypes},
+    };
+
+    use crate::{
+        data::BattleData,
+        endpoint::{create, BattleEndpoint, MpscClient},
+        engine::{BattleEngine, BattlePlayer, ExecuteAction, PlayerQuery},
+        message::ServerMessage,
+        moves::BattleMove,
+        player::PlayerSettings,
+        pokemon::{ActivePosition, Indexed, TeamIndex},
+        select::{BattleSelection, PublicAction, SelectConfirm, SelectMessage},
+    };
+
+    use super::{Battle, BattleState, PlayerData};
+
+    /// An engine that makes the pokemon of the first team use the same move again.
+    struct Engine;
+
+    impl Engine {
+        fn forced_move() -> BattleSelection<u8> {
+            BattleSelection::Move("solarbeam".parse().unwrap(), Some(TeamIndex(2, 0)))
+        }
+    }
+
+    impl BattleEngine<u8, ()> for Engine {
+        type ExecutionError = Infallible;
+
+        type Data = ();
+
+        fn select(
+            &self,
+            _: &mut Self::Data,
+            _: ActivePosition,
+            _: &BattleSelection<u8>,
+            _: &mut BattlePlayer<u8, ()>,
+        ) -> SelectMessage {
+            SelectMessage::Confirm(SelectConfirm::Other)
+        }
+
+        fn execute(
+            &self,
+            _: &mut Self::Data,
+            _: &mut (impl rand::Rng + Clone + Send + Sync + 'static),
+            _: &mut BattleData,
+            _: ExecuteAction<u8>,
+            _: &mut PlayerQuery<u8, ()>,
+        ) -> Result<Vec<Indexed<u8, PublicAction>>, Self::ExecutionError> {
+            Ok(Vec::new())
+        }
+
+        fn post(
+            &self,
+            _: &mut Self::Data,
+            _: &mut (impl rand::Rng + Clone + Send + Sync + 'static),
+            _: &mut BattleData,
+            _: &mut PlayerQuery<u8, ()>,
+        ) -> Result<Vec<Indexed<u8, PublicAction>>, Self::ExecutionError> {
+            Ok(Vec::new())
+        }
+
+        fn forced(&self, _: &Self::Data, pokemon: &TeamIndex<u8>) -> Option<BattleSelection<u8>> {
+            match pokemon.team() {
+                1 => Some(Self::forced_move()),
+                _ => None,
+            }
+        }
+
+        fn reset(&self, _: &mut Self::Data) {}
+
+        fn get_move(&self, _: &MoveId) -> Option<&BattleMove> {
+            None
+        }
+    }
+
+    fn player(id: u8) -> (BattlePlayer<u8, ()>, MpscClient<u8, ()>) {
+        let pokemon = Arc::new(Pokemon {
+            id: PokemonId(1),
+            name: "Bulbasaur".to_owned(),
+            types: PokemonTypes {
+                primary: PokemonType::Grass,
+                secondary: Some(PokemonType::Poison),
+            },
+            moves: vec![],
+            base: StatSet::uniform(50),
+            species: "Seed".to_owned(),
+            evolution: None,
+            height: 7,
+            weight: 69,
+            training: Training {
+                base_exp: 64,
+                growth: GrowthRate::MediumSlow,
+            },
+            breeding: Breeding { gender: Some(1) },
+        });
+
+        let mut pokemon = OwnedPokemon {
+            pokemon,
+            level: 5,
+            gender: Gender::Male,
+            nature: Nature::Hardy,
+            hp: 0,
+            ivs: StatSet::uniform(0),
+            evs: StatSet::uniform(0),
+            friendship: Pokemon::default_friendship(),
+            ailment: None,
+            nickname: None,
+            moves: OwnedMoveSet::default(),
+            item: None,
+            experience: 0,
+        };
+
+        pokemon.heal_hp(None);
+
+        let (client, endpoint) = create();
+
+        let player = PlayerData {
+            id,
+            name: None,
+            party: [pokemon].into_iter().collect(),
+            abilities: Vec::new(),
+            bag: Default::default(),
+            trainer: None,
+            settings: PlayerSettings::default(),
+            endpoint: Arc::new(endpoint),
+        }
+        .init(1);
+
+        (player, client)
+    }
+
+    #[test]
+    fn forced_selections() {
+        let (first, first_client) = player(1);
+        let (second, second_client) = player(2);
+
+        let mut battle = Battle::<u8, (), Engine> {
+            state: BattleState::Selecting(false),
+            data: BattleData::default(),
+            edata: (),
+            players: PlayerQuery::new(vec![first, second]),
+        };
+
+        battle.send_select(&Engine).unwrap();
+
+        let queued = |battle: &Battle<u8, (), Engine>, id: &u8| {
+            battle
+                .players
+                .get(id)
+                .and_then(|p| p.party.active.first())
+                .and_then(Option::as_ref)
+                .and_then(|active| active.queued_move)
+        };
+
+        // a forced selection is queued without asking the player
+        assert_eq!(queued(&battle, &1), Some(Engine::forced_move()));
+        assert!(matches!(first_client.receive(), Ok(None)));
+
+        assert_eq!(queued(&battle, &2), None);
+        assert!(matches!(
+            second_client.receive(),
+            Ok(Some(ServerMessage::Select(0, SelectMessage::Request(None))))
+        ));
+    }
+}
@@ -0,0 +1,399 @@
+//! Declarative battle setup that can be written in any serde format
+
+use alloc::sync::Arc;
+use core::{
+    fmt::{Debug, Display, Formatter, Result as FmtResult},
+    hash::Hash,
+};
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use pokedex::{
+    item::{bag::SavedBag, Item},
+    moves::Move,
+    pokemon::{owned::SavedPokemon, Pokemon},
+    Dex,
+};
+
+use crate::{
+    ai::BattleAi,
+    data::BattleData,
+    endpoint::{create, MpscClient},
+    engine::BattleEngine,
+    player::PlayerSettings,
+    pokemon::AbilityId,
+};
+
+use super::{Battle, PlayerData};
+
+/// Everything needed to create a [Battle].
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BattleDefinition<ID, T> {
+    #[serde(default)]
+    pub data: BattleData,
+    pub players: Vec<PlayerDefinition<ID, T>>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PlayerDefinition<ID, T> {
+    pub id: ID,
+    #[serde(default)]
+    pub name: Option<String>,
+    pub party: Vec<SavedPokemon>,
+    /// Abilities of the party by index
+    #[serde(default)]
+    pub abilities: Vec<Option<AbilityId>>,
+    #[serde(default)]
+    pub bag: SavedBag,
+    #[serde(default)]
+    pub trainer: Option<T>,
+    #[serde(default)]
+    pub settings: PlayerSettings,
+    #[serde(default)]
+    pub control: PlayerControl,
+}
+
+/// Who makes the selections for a player.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum PlayerControl {
+    /// Controlled by a [BattleAi] created by the loader.
+    Ai,
+    /// Controlled by a client connected through the returned endpoint.
+    External,
+}
+
+/// A battle created from a [BattleDefinition].
+pub struct LoadedBattle<
+    ID: Debug + Clone + Ord + Hash + Send + Sync + 'static,
+    T: Clone + Send + Sync,
+    E: BattleEngine<ID, T>,
+> {
+    pub battle: Battle<ID, T, E>,
+    pub ais: Vec<BattleAi<ID, T>>,
+    /// Client side endpoints of [PlayerControl::External] players.
+    pub clients: Vec<(ID, MpscClient<ID, T>)>,
+}
+
+#[derive(Debug)]
+pub enum DefinitionError<ID> {
+    /// A player's pokemon at this party index could not be found in the dexes.
+    Pokemon(ID, usize),
+    /// A player's bag contains an item that is not in the item dex.
+    Bag(ID),
+    /// More than one player has this id.
+    DuplicatePlayer(ID),
+}
+
+impl<ID: Debug + Display> std::error::Error for DefinitionError<ID> {}
+
+impl<ID: Display> Display for DefinitionError<ID> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::Pokemon(id, index) => write!(
+                f,
+                "Pokemon #{} of player {} could not be found in the dexes",
+                index, id
+            ),
+            Self::Bag(id) => write!(
+                f,
+                "The bag of player {} has an item that is not in the item dex",
+                id
+            ),
+            Self::DuplicatePlayer(id) => write!(f, "Player {} is defined more than once", id),
+        }
+    }
+}
+
+impl Default for PlayerControl {
+    fn default() -> Self {
+        Self::External
+    }
+}
+
+impl<ID: Debug + Clone + Ord + Hash + Send + Sync + 'static, T: Clone + Send + Sync + 'static>
+    BattleDefinition<ID, T>
+{
+    pub fn load<E: BattleEngine<ID, T>>(
+        self,
+        random: &mut impl Rng,
+        pokedex: &Dex<Pokemon>,
+        movedex: &Dex<Move>,
+        itemdex: &Dex<Item>,
+    ) -> Result<LoadedBattle<ID, T, E>, DefinitionError<ID>> {
+        let mut ais = Vec::new();
+        let mut clients = Vec::new();
+        let mut players = Vec::with_capacity(self.players.len());
+
+        for player in self.players {
+            if players
+                .iter()
+                .any(|p: &PlayerData<ID, T>| p.id == player.id)
+            {
+                return Err(DefinitionError::DuplicatePlayer(player.id));
+            }
+
+            let party = player
+                .party
+                .into_iter()
+                .enumerate()
+                .map(|(index, pokemon)| {
+                    pokemon
+                        .init(random, pokedex, movedex, itemdex)
+                        .ok_or(index)
+                })
+                .collect::<Result<_, _>>()
+                .map_err(|index| DefinitionError::Pokemon(player.id.clone(), index))?;
+
+            let bag = match player.bag.init(itemdex) {
+                Some(bag) => bag,
+                None => return Err(DefinitionError::Bag(player.id)),
+            };
+
+            let endpoint = match player.control {
+                PlayerControl::Ai => {
+                    let ai = BattleAi::new();
+                    let endpoint = ai.endpoint().clone();
+                    ais.push(ai);
+                    endpoint
+                }
+                PlayerControl::External => {
+                    let (client, endpoint) = create();
+                    clients.push((player.id.clone(), client));
+                    endpoint
+                }
+            };
+
+            players.push(PlayerData {
+                id: player.id,
+                name: player.name,
+                party,
+                abilities: player.abilities,
+                bag,
+                trainer: player.trainer,
+                settings: player.settings,
+                endpoint: Arc::new(endpoint),
+            });
+        }
+
+        Ok(LoadedBattle {
+            battle: Battle::new(self.data, players),
+            ais,
+            clients,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use core::convert::Infallible;
+
+    use rand::rngs::mock::StepRng;
+
+    use pokedex::{
+        item::Item,
+        moves::{owned::SavedMove, Move, MoveId},
+        pokemon::{
+            data::{Breeding, GrowthRate, Training},
+            owned::SavedPokemon,
+            stat::StatSet,
+            Pokemon, PokemonId,
+        },
+        types::{PokemonType, PokemonTypes},
+        Dex,
+    };
+
+    use crate::{
+        data::BattleData,
+        engine::{BattleEngine, BattlePlayer, ExecuteAction, PlayerQuery},
+        moves::BattleMove,
+        pokemon::{ActivePosition, Indexed},
+        select::{BattleSelection, PublicAction, SelectConfirm, SelectMessage},
+    };
+
+    use super::{BattleDefinition, DefinitionError, PlayerControl, PlayerDefinition};
+
+    /// An engine that does nothing, since loading never runs it.
+    struct Engine;
+
+    impl BattleEngine<u8, ()> for Engine {
+        type ExecutionError = Infallible;
+
+        type Data = ();
+
+        fn select(
+            &self,
+            _: &mut Self::Data,
+            _: ActivePosition,
+            _: &BattleSelection<u8>,
+            _: &mut BattlePlayer<u8, ()>,
+        ) -> SelectMessage {
+            SelectMessage::Confirm(SelectConfirm::Other)
+        }
+
+        fn execute(
+            &self,
+            _: &mut Self::Data,
+            _: &mut (impl rand::Rng + Clone + Send + Sync + 'static),
+            _: &mut BattleData,
+            _: ExecuteAction<u8>,
+            _: &mut PlayerQuery<u8, ()>,
+        ) -> Result<Vec<Indexed<u8, PublicAction>>, Self::ExecutionError> {
+            Ok(Vec::new())
+        }
+
+        fn post(
+            &self,
+            _: &mut Self::Data,
+            _: &mut (impl rand::Rng + Clone + Send + Sync + 'static),
+            _: &mut BattleData,
+            _: &mut PlayerQuery<u8, ()>,
+        ) -> Result<Vec<Indexed<u8, PublicAction>>, Self::ExecutionError> {
+            Ok(Vec::new())
+        }
+
+        fn reset(&self, _: &mut Self::Data) {}
+
+        fn get_move(&self, _: &MoveId) -> Option<&BattleMove> {
+            None
+        }
+    }
+
+    struct Dexes {
+        pokedex: Dex<Pokemon>,
+        movedex: Dex<Move>,
+        itemdex: Dex<Item>,
+    }
+
+    fn dexes() -> Dexes {
+        let mut pokedex = Dex::default();
+        pokedex.insert(Pokemon {
+            id: PokemonId(1),
+            name: "Bulbasaur".to_owned(),
+            types: PokemonTypes {
+                primary: PokemonType::Grass,
+                secondary: Some(PokemonType::Poison),
+            },
+            moves: vec![],
+            base: StatSet::uniform(50),
+            species: "Seed".to_owned(),
+            evolution: None,
+            height: 7,
+            weight: 69,
+            training: Training {
+                base_exp: 64,
+                growth: GrowthRate::MediumSlow,
+            },
+            breeding: Breeding { gender: Some(1) },
+        });
+
+        let mut movedex = Dex::default();
+        movedex.insert(Move {
+            id: "tackle".parse().unwrap(),
+            name: "Tackle".to_owned(),
+            pp: 35,
+        });
+
+        Dexes {
+            pokedex,
+            movedex,
+            itemdex: Dex::default(),
+        }
+    }
+
+    fn pokemon() -> SavedPokemon {
+        let mut pokemon = SavedPokemon {
+            pokemon: PokemonId(1),
+            level: 5,
+            ..Default::default()
+        };
+        pokemon
+            .moves
+            .push(SavedMove::from("tackle".parse::<MoveId>().unwrap()));
+        pokemon
+    }
+
+    fn player(
+        id: u8,
+        party: Vec<SavedPokemon>,
+        control: PlayerControl,
+    ) -> PlayerDefinition<u8, ()> {
+        PlayerDefinition {
+            id,
+            name: None,
+            party,
+            abilities: Vec::new(),
+            bag: Default::default(),
+            trainer: None,
+            settings: Default::default(),
+            control,
+        }
+    }
+
+    fn load(players: Vec<PlayerDefinition<u8, ()>>) -> Result<(usize, usize), DefinitionError<u8>> {
+        let dexes = dexes();
+        BattleDefinition {
+            data: Default::default(),
+            players,
+        }
+        .load::<Engine>(
+            &mut StepRng::new(0, 0),
+            &dexes.pokedex,
+            &dexes.movedex,
+            &dexes.itemdex,
+        )
+        .map(|loaded| (loaded.ais.len(), loaded.clients.len()))
+    }
+
+    #[test]
+    fn load_players() {
+        let loaded = load(vec![
+            player(1, vec![pokemon()], PlayerControl::Ai),
+            player(2, vec![pokemon(), pokemon()], PlayerControl::External),
+        ]);
+        assert!(matches!(loaded, Ok((1, 1))));
+    }
+
+    #[test]
+    fn unknown_species() {
+        let unknown = SavedPokemon {
+            pokemon: PokemonId(2),
+            ..pokemon()
+        };
+        let loaded = load(vec![
+            player(1, vec![pokemon()], PlayerControl::Ai),
+            player(2, vec![pokemon(), unknown], PlayerControl::Ai),
+        ]);
+        assert!(matches!(loaded, Err(DefinitionError::Pokemon(2, 1))));
+    }
+
+    #[test]
+    fn unknown_move() {
+        let mut unknown = pokemon();
+        unknown
+            .moves
+            .push(SavedMove::from("ember".parse::<MoveId>().unwrap()));
+        let loaded = load(vec![player(1, vec![unknown], PlayerControl::Ai)]);
+        assert!(matches!(loaded, Err(DefinitionError::Pokemon(1, 0))));
+    }
+
+    #[test]
+    fn unknown_item() {
+        let unknown = SavedPokemon {
+            item: Some("leftovers".parse().unwrap()),
+            ..pokemon()
+        };
+        let loaded = load(vec![player(1, vec![unknown], PlayerControl::Ai)]);
+        assert!(matches!(loaded, Err(DefinitionError::Pokemon(1, 0))));
+    }
+
+    #[test]
+    fn duplicate_players() {
+        let loaded = load(vec![
+            player(1, vec![pokemon()], PlayerControl::Ai),
+            player(1, vec![pokemon()], PlayerControl::External),
+        ]);
+        assert!(matches!(loaded, Err(DefinitionError::DuplicatePlayer(1))));
+    }
+}
@@ -2,13 +2,14 @@ use core::{cmp::Reverse, hash::Hash};
 use rand::Rng;
 use std::collections::BTreeMap;
 
-use pokedex::pokemon::stat::{BaseStat, StatType};
+use pokedex::pokemon::stat::BaseStat;
 
 use crate::{
     engine::{ActiveBattlePokemon, BattleEngine},
     moves::Priority,
     pokemon::{BattlePokemon, Indexed, TeamIndex},
     select::BattleSelection,
+    side::{Side, SideCondition},
 };
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -20,15 +21,20 @@ pub enum MovePriority<ID: Ord> {
 pub fn queue_player<
     ID: Clone + Ord + Hash + Send + Sync + 'static,
     T: Send + Sync + 'static,
+    E: BattleEngine<ID, T>,
     R: Rng,
 >(
-    engine: &impl BattleEngine<ID, T>,
+    engine: &E,
+    data: &E::Data,
     queue: &mut BTreeMap<MovePriority<ID>, Indexed<ID, BattleSelection<ID>>>,
     id: &ID,
     active: &mut [Option<ActiveBattlePokemon<ID>>],
     party: &mut [BattlePokemon],
+    side: &Side,
     random: &mut R,
 ) {
+    let slower_first = engine.slower_first(data);
+
     for index in 0..active.len() {
         if let Some(active) = active.get_mut(index).and_then(Option::as_mut) {
             if let Some(action) = active.queued_move.take() {
@@ -45,7 +51,16 @@ pub fn queue_player<
                                     .and_then(|m| engine.get_move(m.id()).map(|m| m.priority))
                                     .unwrap_or_default(),
                             ),
-                            Reverse(instance.stat(StatType::Speed)),
+                            Reverse({
+                                let mut speed = engine.speed(data, instance);
+                                if side.has(SideCondition::Tailwind) {
+                                    speed = speed.saturating_mul(2);
+                                }
+                                match slower_first {
+                                    true => BaseStat::MAX - speed,
+                                    false => speed,
+                                }
+                            }),
                             None,
                         ),
                         _ => MovePriority::First(id.clone(), index),
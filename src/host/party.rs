@@ -36,6 +36,7 @@ impl<ID, T> BattleParty<ID, T> {
                 .collect(),
             active: ActiveBattlePokemon::as_usize(&self.active),
             trainer: self.trainer.clone(),
+            side: self.side.clone(),
         }
     }
 }
@@ -7,13 +7,15 @@ use crate::{
     engine::{BattlePlayer, PlayerEndpoint},
     party::{ActivePokemon, PlayerParty},
     player::PlayerSettings,
-    pokemon::BattlePokemon,
+    pokemon::{AbilityId, BattlePokemon},
 };
 
 pub struct PlayerData<ID, T> {
     pub id: ID,
     pub name: Option<String>,
     pub party: Party<OwnedPokemon>,
+    /// Abilities of the party by index. Pokemon past the end have no ability.
+    pub abilities: Vec<Option<AbilityId>>,
     pub bag: OwnedBag,
     pub trainer: Option<T>,
     pub settings: PlayerSettings,
@@ -21,8 +23,18 @@ pub struct PlayerData<ID, T> {
 }
 
 impl<ID, T> PlayerData<ID, T> {
-    pub(crate) fn init(self, active: usize) -> BattlePlayer<ID, T> {
-        let pokemon: Party<BattlePokemon> = self.party.into_iter().map(Into::into).collect();
+    pub fn init(self, active: usize) -> BattlePlayer<ID, T> {
+        let mut abilities = self.abilities.into_iter();
+
+        let pokemon: Party<BattlePokemon> = self
+            .party
+            .into_iter()
+            .map(|p| {
+                let mut pokemon = BattlePokemon::from(p);
+                pokemon.ability = abilities.next().flatten();
+                pokemon
+            })
+            .collect();
 
         let mut party = PlayerParty::new(self.id, self.name, active, pokemon, self.trainer);
 
@@ -74,3 +86,88 @@ impl<ID, T> PlayerData<ID, T> {
 //         }
 //     }
 // }
+
+#[cfg(test)]
+mod tests {
+
+    use std::sync::Arc;
+
+    use pokedex::{
+        item::bag::OwnedBag,
+        moves::set::OwnedMoveSet,
+        pokemon::{
+            data::{Breeding, Gender, GrowthRate, Training},
+            owned::OwnedPokemon,
+            stat::StatSet,
+            Nature, Pokemon, PokemonId,
+        },
+        types::{PokemonType, PokemonTypes},
+    };
+
+    use crate::{endpoint::create, player::PlayerSettings};
+
+    use super::PlayerData;
+
+    #[test]
+    fn abilities() {
+        let pokemon = Arc::new(Pokemon {
+            id: PokemonId(25),
+            name: "Pikachu".to_owned(),
+            types: PokemonTypes {
+                primary: PokemonType::Electric,
+                secondary: None,
+            },
+            moves: vec![],
+            base: StatSet::uniform(50),
+            species: "Mouse".to_owned(),
+            evolution: None,
+            height: 4,
+            weight: 60,
+            training: Training {
+                base_exp: 112,
+                growth: GrowthRate::MediumSlow,
+            },
+            breeding: Breeding { gender: Some(4) },
+        });
+
+        let owned = OwnedPokemon {
+            pokemon,
+            level: 5,
+            gender: Gender::Male,
+            nature: Nature::Hardy,
+            hp: 1,
+            ivs: StatSet::uniform(0),
+            evs: StatSet::uniform(0),
+            friendship: Pokemon::default_friendship(),
+            ailment: None,
+            nickname: None,
+            moves: OwnedMoveSet::default(),
+            item: None,
+            experience: 0,
+        };
+
+        let ability = "static".parse().unwrap();
+
+        // two pokemon of the same species with different abilities
+        let player = PlayerData::<u8, ()> {
+            id: 0,
+            name: None,
+            party: [owned.clone(), owned.clone(), owned].into_iter().collect(),
+            abilities: vec![Some(ability), None],
+            bag: OwnedBag::default(),
+            trainer: None,
+            settings: PlayerSettings::default(),
+            endpoint: Arc::new(create().1),
+        }
+        .init(1);
+
+        let abilities = player
+            .party
+            .pokemon
+            .iter()
+            .map(|pokemon| pokemon.ability)
+            .collect::<Vec<_>>();
+
+        assert_eq!(abilities, vec![Some(ability), None, None]);
+    }
+}
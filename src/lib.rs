@@ -5,12 +5,14 @@ extern crate alloc;
 pub extern crate firecore_pokedex as pokedex;
 
 pub mod select;
+pub mod client;
 pub mod data;
 pub mod endpoint;
 pub mod message;
 pub mod party;
 pub mod player;
 pub mod pokemon;
+pub mod side;
 pub mod moves;
 
 #[cfg(feature = "host")]
@@ -1,6 +1,11 @@
 use pokedex::{moves::MoveId, pokemon::{Health, stat::StatType}, types::*};
 use serde::{Deserialize, Serialize};
 
+use crate::{
+    data::{BattleData, Weather},
+    side::Side,
+};
+
 /// How powerful a [Move] is, in points. Some moves do not use power levels.
 pub type Power = u8;
 /// How accurate a [Move] is, in values 0 - 100.
@@ -18,6 +23,8 @@ pub type Critical = bool;
 pub type Percent = u8;
 
 pub type MoveCancelId = tinystr::TinyAsciiStr<8>;
+/// Reason a move is cancelled when it fails
+pub const FAILED: MoveCancelId = tinystr::tinystr!(8, "fail");
 /// remove pokemon from ability to be active
 pub type RemovePokemonId = tinystr::TinyAsciiStr<8>;
 
@@ -153,6 +160,88 @@ pub struct DamageResult<N> {
     pub crit: bool,
 }
 
+/// Conditions of the battle that change the damage of a move.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct DamageModifiers {
+    pub weather: Option<Weather>,
+    /// Multipliers of the damage of moves by their type, such as from a terrain.
+    pub types: Vec<(PokemonType, f32)>,
+    /// Categories of moves that deal half damage, from screens on the target's side.
+    pub screens: Vec<MoveCategory>,
+    /// Types of moves that do not affect the target, such as from its ability.
+    pub immune: Vec<PokemonType>,
+    /// Multipliers of the damage of moves the user uses by their type, such as from its ability.
+    pub attack: Vec<(PokemonType, f32)>,
+    /// Multipliers of the user's stats, such as from its held item.
+    pub stats: Vec<(StatType, f32)>,
+    /// Multipliers of the damage of every move the user uses, such as from its held item.
+    pub boosts: Vec<f32>,
+}
+
+impl DamageModifiers {
+    /// Add the screens of the target's side.
+    pub fn side(mut self, side: &Side) -> Self {
+        self.screens = side.screens();
+        self
+    }
+
+    /// Use the attack multipliers of the user's modifiers.
+    pub fn attacker(mut self, user: &DamageModifiers) -> Self {
+        self.attack = user.attack.clone();
+        self.stats = user.stats.clone();
+        self.boosts = user.boosts.clone();
+        self
+    }
+
+    /// How much the damage of a move of a type is multiplied by.
+    pub fn multiplier(&self, move_type: PokemonType) -> f64 {
+        self.types
+            .iter()
+            .chain(self.attack.iter())
+            .filter(|(t, ..)| *t == move_type)
+            .map(|(.., m)| *m as f64)
+            .product::<f64>()
+            * self.boosts.iter().map(|m| *m as f64).product::<f64>()
+            * self.weather.map(|w| w.damage(move_type)).unwrap_or(1.0)
+    }
+
+    /// How much a stat of the user is multiplied by.
+    pub fn stat(&self, stat: StatType) -> f64 {
+        self.stats
+            .iter()
+            .filter(|(s, ..)| *s == stat)
+            .map(|(.., m)| *m as f64)
+            .product()
+    }
+
+    /// How [Effective] a move is on the target, taking its immunities into account.
+    pub fn effective(
+        &self,
+        category: MoveCategory,
+        move_type: PokemonType,
+        target: PokemonTypes,
+    ) -> Effective {
+        match self.immune.contains(&move_type) {
+            true => Effective::Ineffective,
+            false => category.effective(move_type, target),
+        }
+    }
+}
+
+impl From<&BattleData> for DamageModifiers {
+    fn from(data: &BattleData) -> Self {
+        Self {
+            weather: data.weather(),
+            types: Vec::new(),
+            screens: Vec::new(),
+            immune: Vec::new(),
+            attack: Vec::new(),
+            stats: Vec::new(),
+            boosts: Vec::new(),
+        }
+    }
+}
+
 impl<N> ClientDamage<N> {
     pub fn damage(self) -> N {
         match self {
@@ -4,7 +4,10 @@ use serde::{Deserialize, Serialize};
 
 use pokedex::pokemon::party::Party;
 
-use crate::pokemon::{remote::RemotePokemon, PokemonInstance};
+use crate::{
+    pokemon::{remote::RemotePokemon, PokemonInstance},
+    side::Side,
+};
 
 pub type RemoteParty<ID, T> = crate::party::PlayerParty<ID, usize, Option<RemotePokemon>, T>;
 
@@ -17,6 +20,8 @@ pub struct PlayerParty<ID, A, P, T> {
     pub active: Active<A>,
     pub pokemon: Party<P>,
     pub trainer: Option<T>,
+    #[serde(default)]
+    pub side: Side,
 }
 
 /// Get the index of the pokemon in the party from this type.
@@ -124,6 +129,7 @@ impl<ID, A: ActivePokemon, P: PokemonInstance, T> PlayerParty<ID, A, P, T> {
             active,
             pokemon,
             trainer,
+            side: Default::default(),
         }
     }
 
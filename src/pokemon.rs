@@ -4,6 +4,7 @@ use rand::Rng;
 use serde::{Deserialize, Serialize};
 
 use pokedex::{
+    ailment::Ailment,
     moves::MoveId,
     pokemon::{
         owned::{OwnedPokemon, SavedPokemon},
@@ -14,16 +15,22 @@ use pokedex::{
 };
 
 use crate::{
-    data::VersusType,
-    moves::{Accuracy, CriticalRate, DamageKind, DamageResult, MoveCategory, Percent, Power},
+    data::{VersusType, Weather},
+    moves::{
+        Accuracy, CriticalRate, DamageKind, DamageModifiers, DamageResult, MoveCategory, Percent,
+        Power,
+    },
 };
 
 pub mod remote;
 pub mod stat;
+pub mod volatile;
 
 pub type ActivePosition = usize;
 pub type PartyPosition = usize;
 
+pub type AbilityId = tinystr::TinyAsciiStr<16>;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
 pub struct TeamIndex<ID>(pub ID, pub usize);
 
@@ -79,10 +86,23 @@ pub fn damage_range(random: &mut impl Rng) -> Percent {
     random.gen_range(85..=100u8)
 }
 
+/// Turns of a pokemon's ailment that are counted in battle.
+/// Reset whenever the ailment changes.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct AilmentCounter {
+    /// Turns left until the pokemon wakes up, decided the first time it tries to move.
+    pub sleep: Option<u8>,
+    /// Turns the pokemon has been badly poisoned for
+    pub toxic: Option<u8>,
+}
+
 #[derive(Debug, Clone)]
 pub struct BattlePokemon {
     pub p: OwnedPokemon,
     pub stages: stat::StatStages,
+    pub volatile: volatile::Volatile,
+    pub ability: Option<AbilityId>,
+    pub ailment_counter: AilmentCounter,
     pub(crate) learnable: HashSet<MoveId>,
     revealed: bool,
 }
@@ -157,12 +177,15 @@ impl BattlePokemon {
         category: MoveCategory,
         move_type: PokemonType,
         crit_rate: CriticalRate,
+        modifiers: &DamageModifiers,
     ) -> DamageResult<Health> {
-        let effective = category.effective(move_type, target.pokemon.types);
-        let crit = crit(random, crit_rate);
+        let effective = modifiers.effective(category, move_type, target.pokemon.types);
+        let crit = crit(random, self.crit_rate(crit_rate));
 
         if let DamageKind::Power(power) = kind {
-            self.move_power_damage_random(random, target, power, category, move_type, crit)
+            self.move_power_damage_random(
+                random, target, power, category, move_type, crit, modifiers,
+            )
         } else {
             DamageResult {
                 damage: match matches!(effective, Effective::Ineffective) {
@@ -194,6 +217,7 @@ impl BattlePokemon {
         category: MoveCategory,
         move_type: PokemonType,
         crit: bool,
+        modifiers: &DamageModifiers,
     ) -> DamageResult<Health> {
         self.move_power_damage(
             target,
@@ -202,6 +226,7 @@ impl BattlePokemon {
             move_type,
             crit,
             damage_range(random),
+            modifiers,
         )
     }
 
@@ -213,10 +238,11 @@ impl BattlePokemon {
         move_type: PokemonType,
         crit: bool,
         range: u8,
+        modifiers: &DamageModifiers,
     ) -> DamageResult<Health> {
-        let effective = category.effective(move_type, target.pokemon.types);
-        let (attack, defense) = category.stats();
-        let attack = self.stat(attack);
+        let effective = modifiers.effective(category, move_type, target.pokemon.types);
+        let (attack_stat, defense) = category.stats();
+        let attack = self.stat(attack_stat) as f64 * modifiers.stat(attack_stat);
         let defense = target.stat(defense);
         if matches!(effective, Effective::Ineffective) {
             return DamageResult::default();
@@ -234,7 +260,7 @@ impl BattlePokemon {
         damage += 2.0;
         damage = damage.floor();
         damage *= power as f64;
-        damage *= attack as f64 / defense as f64;
+        damage *= attack / defense as f64;
         damage = damage.floor();
         damage /= 50.0;
         damage = damage.floor();
@@ -245,6 +271,16 @@ impl BattlePokemon {
         damage *= crit_dmg(crit);
         damage *= effective.multiplier() as f64;
 
+        damage *= modifiers.multiplier(move_type);
+
+        if !crit && modifiers.screens.contains(&category) {
+            damage *= 0.5;
+        }
+
+        if category == MoveCategory::Physical && self.has_ailment(Ailment::Burn) {
+            damage *= 0.5;
+        }
+
         // println!(
         //     "PWR: {}, LVL: {}, ATK: {}, DEF: {}, DMG: {}",
         //     power, self.level, attack, defense, damage
@@ -257,6 +293,73 @@ impl BattlePokemon {
         }
     }
 
+    /// Critical hit rate of a move, which Focus Energy raises.
+    pub fn crit_rate(&self, rate: CriticalRate) -> CriticalRate {
+        match self.volatile.focus_energy {
+            true => rate.saturating_add(2),
+            false => rate,
+        }
+    }
+
+    /// Clear everything that only lasts while the pokemon is on the field.
+    pub fn switch_out(&mut self) {
+        self.stages = Default::default();
+        self.volatile = Default::default();
+        // badly poisoned pokemon start counting again when they come back
+        if let Some(toxic) = self.ailment_counter.toxic.as_mut() {
+            *toxic = 1;
+        }
+    }
+
+    pub fn has_ailment(&self, ailment: Ailment) -> bool {
+        self.ailment.map(|a| a.ailment == ailment).unwrap_or_default()
+    }
+
+    /// Speed when moves are ordered, which paralysis halves.
+    pub fn speed(&self) -> BaseStat {
+        let speed = self.stat(StatType::Speed);
+        match self.has_ailment(Ailment::Paralysis) {
+            true => speed / 2,
+            false => speed,
+        }
+    }
+
+    /// Damage taken from burn or poison at the end of a turn.
+    pub fn ailment_damage(&self) -> Option<Health> {
+        let max = self.max_hp();
+        let damage = match self.ailment?.ailment {
+            Ailment::Burn => max / 16,
+            Ailment::Poison => match self.ailment_counter.toxic {
+                Some(turns) => max / 16 * turns.min(15) as Health,
+                None => max / 8,
+            },
+            _ => return None,
+        };
+        Some(damage.max(1))
+    }
+
+    pub fn is_type(&self, pokemon_type: PokemonType) -> bool {
+        let types = self.pokemon.types;
+        types.primary == pokemon_type || types.secondary == Some(pokemon_type)
+    }
+
+    /// If the pokemon touches the ground, which Flying types do not.
+    pub fn grounded(&self) -> bool {
+        !self.is_type(PokemonType::Flying)
+    }
+
+    /// Damage taken from weather at the end of a turn.
+    pub fn weather_damage(&self, weather: Weather) -> Option<Health> {
+        let types = self.pokemon.types;
+        match core::iter::once(types.primary)
+            .chain(types.secondary)
+            .any(|t| weather.immune(t))
+        {
+            true => None,
+            false => Some((self.max_hp() / 16).max(1)),
+        }
+    }
+
     pub fn reveal(&mut self) {
         self.revealed = true;
     }
@@ -276,6 +379,9 @@ impl From<OwnedPokemon> for BattlePokemon {
         Self {
             p,
             stages: Default::default(),
+            volatile: Default::default(),
+            ability: None,
+            ailment_counter: Default::default(),
             learnable: Default::default(),
             revealed: false,
         }
@@ -325,7 +431,10 @@ mod tests {
 
     use std::sync::Arc;
 
+    use rand::rngs::mock::StepRng;
+
     use firecore_pokedex::{
+        ailment::{Ailment, AilmentLength},
         moves::set::OwnedMoveSet,
         pokemon::{
             data::{Breeding, Gender, GrowthRate, Training},
@@ -445,6 +554,7 @@ mod tests {
                 PokemonType::Water,
                 false,
                 100,
+                &Default::default(),
             )
             .damage;
         assert!(damage <= 1200, "Damage passed threshold! {} > 1200", damage);
@@ -454,4 +564,65 @@ mod tests {
             damage
         );
     }
+
+    #[test]
+    fn toxic() {
+        let pokemon = Arc::new(Pokemon {
+            id: PokemonId(1),
+            name: "Bulbasaur".to_owned(),
+            types: PokemonTypes {
+                primary: PokemonType::Grass,
+                secondary: Some(PokemonType::Poison),
+            },
+            moves: vec![],
+            base: StatSet::uniform(50),
+            species: "Seed".to_owned(),
+            evolution: None,
+            height: 7,
+            weight: 69,
+            training: Training {
+                base_exp: 64,
+                growth: GrowthRate::MediumSlow,
+            },
+            breeding: Breeding { gender: Some(1) },
+        });
+
+        let mut pokemon = OwnedPokemon {
+            pokemon,
+            level: 50,
+            gender: Gender::Male,
+            nature: Nature::Hardy,
+            hp: 0,
+            ivs: StatSet::uniform(0),
+            evs: StatSet::uniform(0),
+            friendship: Pokemon::default_friendship(),
+            ailment: Some(AilmentLength::Permanent.init(Ailment::Poison, &mut StepRng::new(0, 0))),
+            nickname: None,
+            moves: OwnedMoveSet::default(),
+            item: None,
+            experience: 0,
+        };
+
+        pokemon.heal_hp(None);
+
+        let mut pokemon = BattlePokemon::from(pokemon);
+        let max = pokemon.max_hp();
+
+        assert_eq!(pokemon.ailment_damage(), Some(max / 8));
+
+        pokemon.ailment_counter.toxic = Some(1);
+
+        // the damage grows each turn it is badly poisoned
+        for turns in 1..=3 {
+            assert_eq!(pokemon.ailment_damage(), Some(max / 16 * turns));
+            if let Some(toxic) = pokemon.ailment_counter.toxic.as_mut() {
+                *toxic += 1;
+            }
+        }
+
+        pokemon.switch_out();
+
+        assert_eq!(pokemon.ailment_counter.toxic, Some(1));
+        assert_eq!(pokemon.ailment_damage(), Some(max / 16));
+    }
 }
@@ -4,10 +4,13 @@ use serde::{Deserialize, Serialize};
 
 use pokedex::{
     ailment::LiveAilment,
+    item::ItemId,
     pokemon::{data::Gender, owned::OwnedPokemon, Level, Pokemon, PokemonId},
     Dex,
 };
 
+use super::AbilityId;
+
 pub type RemotePokemon = UnknownPokemon<PokemonId>;
 pub type InitUnknownPokemon = UnknownPokemon<Arc<Pokemon>>;
 
@@ -19,6 +22,12 @@ pub struct UnknownPokemon<P> {
     pub gender: Gender,
     pub hp: f32,
     pub ailment: Option<LiveAilment>,
+    /// Only known once it has been revealed
+    #[serde(default)]
+    pub ability: Option<AbilityId>,
+    /// Only known once it has been revealed
+    #[serde(default)]
+    pub item: Option<ItemId>,
 }
 
 impl<P> UnknownPokemon<P> {
@@ -36,6 +45,8 @@ impl InitUnknownPokemon {
             gender: pokemon.gender,
             hp: pokemon.percent_hp(),
             ailment: pokemon.ailment,
+            ability: None,
+            item: None,
         }
     }
 
@@ -57,6 +68,8 @@ impl InitUnknownPokemon {
             gender: self.gender,
             hp: self.hp,
             ailment: self.ailment,
+            ability: self.ability,
+            item: self.item,
         }
     }
 }
@@ -70,6 +83,8 @@ impl RemotePokemon {
             gender: self.gender,
             hp: self.hp,
             ailment: self.ailment,
+            ability: self.ability,
+            item: self.item,
         })
     }
 }
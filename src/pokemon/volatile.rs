@@ -0,0 +1,156 @@
+//! Conditions of a pokemon that last until it leaves the field
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use pokedex::pokemon::Health;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum VolatileStatus {
+    /// Might hurt itself instead of moving
+    Confusion,
+    /// Loses HP to the pokemon that seeded it each turn
+    LeechSeed,
+    /// A decoy takes hits in its place
+    Substitute,
+    /// Loses a quarter of its max HP each turn
+    Curse,
+    /// Might be too in love to move
+    Infatuation,
+    /// Lands critical hits more often
+    FocusEnergy,
+    /// Faints when the count runs out
+    PerishSong,
+    /// Cannot move for the rest of the turn
+    Flinch,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VolatileEvent {
+    Start(VolatileStatus),
+    End(VolatileStatus),
+    /// Hurt by a status, such as by hitting itself in confusion
+    Hurt(VolatileStatus),
+    /// Could not move because of a status
+    Immobile(VolatileStatus),
+    /// Its substitute took a hit
+    Blocked,
+    /// The perish count fell to a number of turns
+    Perish(u8),
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Volatile {
+    /// Turns left until confusion ends
+    pub confusion: Option<u8>,
+    pub seeded: bool,
+    /// HP left of the substitute
+    pub substitute: Option<Health>,
+    pub cursed: bool,
+    pub infatuated: bool,
+    pub focus_energy: bool,
+    /// Turns left until the pokemon faints
+    pub perish: Option<u8>,
+    pub flinch: bool,
+}
+
+impl Volatile {
+    pub const PERISH_TURNS: u8 = 3;
+
+    pub fn has(&self, status: VolatileStatus) -> bool {
+        match status {
+            VolatileStatus::Confusion => self.confusion.is_some(),
+            VolatileStatus::LeechSeed => self.seeded,
+            VolatileStatus::Substitute => self.substitute.is_some(),
+            VolatileStatus::Curse => self.cursed,
+            VolatileStatus::Infatuation => self.infatuated,
+            VolatileStatus::FocusEnergy => self.focus_energy,
+            VolatileStatus::PerishSong => self.perish.is_some(),
+            VolatileStatus::Flinch => self.flinch,
+        }
+    }
+
+    /// Start a status, returning false if the pokemon already has it.
+    /// A substitute starts with the HP given.
+    pub fn start(
+        &mut self,
+        status: VolatileStatus,
+        substitute: Health,
+        random: &mut impl Rng,
+    ) -> bool {
+        if self.has(status) {
+            return false;
+        }
+        match status {
+            VolatileStatus::Confusion => self.confusion = Some(random.gen_range(2..=5)),
+            VolatileStatus::LeechSeed => self.seeded = true,
+            VolatileStatus::Substitute => self.substitute = Some(substitute),
+            VolatileStatus::Curse => self.cursed = true,
+            VolatileStatus::Infatuation => self.infatuated = true,
+            VolatileStatus::FocusEnergy => self.focus_energy = true,
+            VolatileStatus::PerishSong => self.perish = Some(Self::PERISH_TURNS),
+            VolatileStatus::Flinch => self.flinch = true,
+        }
+        true
+    }
+
+    /// End a status, returning false if the pokemon did not have it.
+    pub fn end(&mut self, status: VolatileStatus) -> bool {
+        let had = self.has(status);
+        match status {
+            VolatileStatus::Confusion => self.confusion = None,
+            VolatileStatus::LeechSeed => self.seeded = false,
+            VolatileStatus::Substitute => self.substitute = None,
+            VolatileStatus::Curse => self.cursed = false,
+            VolatileStatus::Infatuation => self.infatuated = false,
+            VolatileStatus::FocusEnergy => self.focus_energy = false,
+            VolatileStatus::PerishSong => self.perish = None,
+            VolatileStatus::Flinch => self.flinch = false,
+        }
+        had
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use rand::rngs::mock::StepRng;
+
+    use super::{Volatile, VolatileStatus};
+
+    const STATUSES: [VolatileStatus; 8] = [
+        VolatileStatus::Confusion,
+        VolatileStatus::LeechSeed,
+        VolatileStatus::Substitute,
+        VolatileStatus::Curse,
+        VolatileStatus::Infatuation,
+        VolatileStatus::FocusEnergy,
+        VolatileStatus::PerishSong,
+        VolatileStatus::Flinch,
+    ];
+
+    #[test]
+    fn start_end() {
+        let mut random = StepRng::new(0, 1);
+        let mut volatile = Volatile::default();
+
+        for status in STATUSES {
+            assert!(!volatile.has(status));
+            assert!(volatile.start(status, 25, &mut random));
+            assert!(volatile.has(status), "{:?} did not start!", status);
+            assert!(!volatile.start(status, 25, &mut random));
+        }
+
+        assert!(matches!(volatile.confusion, Some(2..=5)));
+        assert_eq!(volatile.substitute, Some(25));
+        assert_eq!(volatile.perish, Some(Volatile::PERISH_TURNS));
+
+        for status in STATUSES {
+            assert!(volatile.end(status));
+            assert!(!volatile.has(status), "{:?} did not end!", status);
+            assert!(!volatile.end(status));
+        }
+
+        assert_eq!(volatile, Volatile::default());
+    }
+}
@@ -1,18 +1,21 @@
 use serde::{Deserialize, Serialize};
 
 use pokedex::{
-    ailment::LiveAilment,
+    ailment::{Ailment, LiveAilment},
     item::ItemId,
     moves::{MoveId, PP},
     pokemon::{Experience},
 };
 
 use crate::{
+    data::{FieldEvent, WeatherEvent},
     moves::{ClientDamage, MoveCancelId, RemovePokemonId},
     pokemon::{
         stat::{BattleStatType, Stage},
-        Indexed, PartyPosition, TeamIndex,
+        volatile::VolatileEvent,
+        AbilityId, Indexed, PartyPosition, TeamIndex,
     },
+    side::SideEvent,
 };
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -46,6 +49,8 @@ pub enum SelectReason {
     MissingPokemon,
     MissingActive,
     FaintedPokemon,
+    /// The pokemon can only use one of its moves
+    Locked,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -54,6 +59,10 @@ pub enum ClientAction<ID> {
     Announce(usize, Option<TeamIndex<ID>>, ClientActionType<ID>),
     /// vector of targets of client move action
     Actions(Vec<Indexed<ID, PublicAction>>),
+    /// weather changes that are not caused by a pokemon
+    Weather(WeatherEvent),
+    /// field conditions that ended on their own
+    Field(FieldEvent),
     Error(#[serde(skip)] String),
 }
 
@@ -63,6 +72,8 @@ pub enum ClientActionType<ID> {
     Move(MoveId),
     Pokemon(PartyPosition),
     Item(Indexed<ID, ItemId>),
+    /// The pokemon spends its turn recharging from its last move
+    Recharge,
     Post,
 }
 
@@ -77,6 +88,27 @@ pub enum PublicAction {
     Reveal,
     Cancel(MoveCancelId),
     Remove(RemovePokemonId),
+    Weather(WeatherEvent),
+    Field(FieldEvent),
+    /// Only the team of the pokemon matters
+    Side(SideEvent),
+    /// The pokemon's ability activated
+    Ability(AbilityId),
+    /// The pokemon's held item activated, and if it was used up
+    Item(ItemId, bool),
+    /// The pokemon was hurt by its ailment
+    Hurt(Ailment),
+    /// The pokemon could not move because of its ailment
+    Immobile(Ailment),
+    /// The pokemon got over its ailment by itself, such as by waking up
+    Recover(Ailment),
+    Volatile(VolatileEvent),
+    /// The pokemon hit its target a number of times with a multi-hit move
+    Hits(u8),
+    /// The pokemon is charging its move and will use it next turn
+    Charge,
+    /// The pokemon has to recharge and cannot move
+    Recharge,
     Miss,
 }
 
@@ -0,0 +1,299 @@
+//! Conditions on one side of the field
+
+use serde::{Deserialize, Serialize};
+
+use pokedex::{pokemon::Health, types::PokemonType};
+
+use crate::{moves::MoveCategory, pokemon::BattlePokemon};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SideCondition {
+    /// Halves physical damage
+    Reflect,
+    /// Halves special damage
+    LightScreen,
+    /// Doubles speed
+    Tailwind,
+    /// Prevents ailments
+    Safeguard,
+}
+
+/// Hurts pokemon entering the field
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Hazard {
+    Spikes,
+    StealthRock,
+    ToxicSpikes,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SideEvent {
+    Start(SideCondition),
+    End(SideCondition),
+    /// Add a layer of a hazard
+    Hazard(Hazard),
+    /// Remove every layer of a hazard
+    Clear(Hazard),
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Side {
+    /// Conditions in effect and the turns they have left
+    pub conditions: Vec<(SideCondition, u8)>,
+    /// Hazards and how many layers of each are set
+    pub hazards: Vec<(Hazard, u8)>,
+}
+
+impl SideCondition {
+    pub const fn turns(&self) -> u8 {
+        match self {
+            Self::Tailwind => 4,
+            Self::Reflect | Self::LightScreen | Self::Safeguard => 5,
+        }
+    }
+}
+
+impl Hazard {
+    pub const fn max_layers(&self) -> u8 {
+        match self {
+            Self::Spikes => 3,
+            Self::StealthRock => 1,
+            Self::ToxicSpikes => 2,
+        }
+    }
+}
+
+impl Side {
+    pub fn has(&self, condition: SideCondition) -> bool {
+        self.conditions.iter().any(|(c, ..)| c == &condition)
+    }
+
+    pub fn layers(&self, hazard: Hazard) -> u8 {
+        self.hazards
+            .iter()
+            .find(|(h, ..)| h == &hazard)
+            .map(|(.., layers)| *layers)
+            .unwrap_or_default()
+    }
+
+    /// Apply an event to the side, returning false if nothing changed.
+    pub fn apply(&mut self, event: SideEvent) -> bool {
+        match event {
+            SideEvent::Start(condition) => match self.has(condition) {
+                true => false,
+                false => {
+                    self.conditions.push((condition, condition.turns()));
+                    true
+                }
+            },
+            SideEvent::End(condition) => {
+                let len = self.conditions.len();
+                self.conditions.retain(|(c, ..)| c != &condition);
+                self.conditions.len() != len
+            }
+            SideEvent::Hazard(hazard) => match self.hazards.iter_mut().find(|(h, ..)| h == &hazard)
+            {
+                Some((.., layers)) if *layers >= hazard.max_layers() => false,
+                Some((.., layers)) => {
+                    *layers += 1;
+                    true
+                }
+                None => {
+                    self.hazards.push((hazard, 1));
+                    true
+                }
+            },
+            SideEvent::Clear(hazard) => {
+                let len = self.hazards.len();
+                self.hazards.retain(|(h, ..)| h != &hazard);
+                self.hazards.len() != len
+            }
+        }
+    }
+
+    /// Count down a turn, returning the conditions that ended.
+    pub fn tick(&mut self) -> Vec<SideCondition> {
+        let mut ended = Vec::new();
+        self.conditions.retain_mut(|(condition, turns)| {
+            *turns = turns.saturating_sub(1);
+            match *turns == 0 {
+                true => {
+                    ended.push(*condition);
+                    false
+                }
+                false => true,
+            }
+        });
+        ended
+    }
+
+    /// Categories of moves that deal half damage to pokemon on this side.
+    pub fn screens(&self) -> Vec<MoveCategory> {
+        self.conditions
+            .iter()
+            .flat_map(|(condition, ..)| match condition {
+                SideCondition::Reflect => Some(MoveCategory::Physical),
+                SideCondition::LightScreen => Some(MoveCategory::Special),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Damage Spikes and Stealth Rock deal to a pokemon entering the field.
+    pub fn hazard_damage(&self, pokemon: &BattlePokemon) -> Health {
+        let max = pokemon.max_hp() as f32;
+
+        let spikes = match pokemon.grounded() {
+            true => match self.layers(Hazard::Spikes) {
+                0 => 0.0,
+                1 => max / 8.0,
+                2 => max / 6.0,
+                _ => max / 4.0,
+            },
+            false => 0.0,
+        };
+
+        let rocks = match self.layers(Hazard::StealthRock) {
+            0 => 0.0,
+            _ => {
+                MoveCategory::Physical
+                    .effective(PokemonType::Rock, pokemon.pokemon.types)
+                    .multiplier()
+                    * max
+                    / 8.0
+            }
+        };
+
+        (spikes + rocks) as Health
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::sync::Arc;
+
+    use pokedex::{
+        moves::set::OwnedMoveSet,
+        pokemon::{
+            data::{Breeding, Gender, GrowthRate, Training},
+            owned::OwnedPokemon,
+            stat::StatSet,
+            Nature, Pokemon, PokemonId,
+        },
+        types::{PokemonType, PokemonTypes},
+    };
+
+    use crate::{moves::MoveCategory, pokemon::BattlePokemon};
+
+    use super::{Hazard, Side, SideCondition, SideEvent};
+
+    fn pokemon(types: PokemonTypes) -> BattlePokemon {
+        let pokemon = Arc::new(Pokemon {
+            id: PokemonId(1),
+            name: "Test".to_owned(),
+            types,
+            moves: vec![],
+            base: StatSet::uniform(80),
+            species: "Test".to_owned(),
+            evolution: None,
+            height: 10,
+            weight: 100,
+            training: Training {
+                base_exp: 64,
+                growth: GrowthRate::MediumSlow,
+            },
+            breeding: Breeding { gender: Some(4) },
+        });
+
+        let mut pokemon = OwnedPokemon {
+            pokemon,
+            level: 50,
+            gender: Gender::Male,
+            nature: Nature::Hardy,
+            hp: 0,
+            ivs: StatSet::uniform(0),
+            evs: StatSet::uniform(0),
+            friendship: Pokemon::default_friendship(),
+            ailment: None,
+            nickname: None,
+            moves: OwnedMoveSet::default(),
+            item: None,
+            experience: 0,
+        };
+
+        pokemon.heal_hp(None);
+
+        pokemon.into()
+    }
+
+    #[test]
+    fn conditions() {
+        let mut side = Side::default();
+
+        assert!(side.apply(SideEvent::Start(SideCondition::Reflect)));
+        assert!(!side.apply(SideEvent::Start(SideCondition::Reflect)));
+        assert!(side.apply(SideEvent::Start(SideCondition::Tailwind)));
+        assert_eq!(side.screens(), vec![MoveCategory::Physical]);
+
+        for _ in 1..SideCondition::Tailwind.turns() {
+            assert!(side.tick().is_empty());
+        }
+        assert_eq!(side.tick(), vec![SideCondition::Tailwind]);
+        assert!(side.has(SideCondition::Reflect));
+        assert_eq!(side.tick(), vec![SideCondition::Reflect]);
+        assert!(side.conditions.is_empty());
+
+        assert!(side.apply(SideEvent::Start(SideCondition::Safeguard)));
+        assert!(side.apply(SideEvent::End(SideCondition::Safeguard)));
+        assert!(!side.apply(SideEvent::End(SideCondition::Safeguard)));
+    }
+
+    #[test]
+    fn hazards() {
+        let mut side = Side::default();
+
+        for layer in 1..=Hazard::Spikes.max_layers() {
+            assert!(side.apply(SideEvent::Hazard(Hazard::Spikes)));
+            assert_eq!(side.layers(Hazard::Spikes), layer);
+        }
+        assert!(!side.apply(SideEvent::Hazard(Hazard::Spikes)));
+        assert_eq!(side.layers(Hazard::Spikes), Hazard::Spikes.max_layers());
+
+        assert!(side.apply(SideEvent::Clear(Hazard::Spikes)));
+        assert!(!side.apply(SideEvent::Clear(Hazard::Spikes)));
+        assert_eq!(side.layers(Hazard::Spikes), 0);
+    }
+
+    #[test]
+    fn hazard_damage() {
+        let normal = pokemon(PokemonTypes {
+            primary: PokemonType::Normal,
+            secondary: None,
+        });
+        let flying = pokemon(PokemonTypes {
+            primary: PokemonType::Fire,
+            secondary: Some(PokemonType::Flying),
+        });
+
+        let mut side = Side::default();
+        assert_eq!(side.hazard_damage(&normal), 0);
+
+        side.apply(SideEvent::Hazard(Hazard::Spikes));
+        assert_eq!(side.hazard_damage(&normal), normal.max_hp() / 8);
+        assert_eq!(
+            side.hazard_damage(&flying),
+            0,
+            "Spikes hurt a flying pokemon!"
+        );
+
+        side.apply(SideEvent::Clear(Hazard::Spikes));
+        side.apply(SideEvent::Hazard(Hazard::StealthRock));
+        assert_eq!(side.hazard_damage(&normal), normal.max_hp() / 8);
+        // rock is super effective against both fire and flying
+        assert_eq!(side.hazard_damage(&flying), flying.max_hp() / 2);
+
+        side.apply(SideEvent::Hazard(Hazard::ToxicSpikes));
+        assert_eq!(side.hazard_damage(&normal), normal.max_hp() / 8);
+    }
+}
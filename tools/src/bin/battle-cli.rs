@@ -0,0 +1,484 @@
+//! Play a battle against the AI from the terminal.
+//!
+//! Usage: `battle-cli <data directory>`
+//!
+//! The directory holds `pokedex.ron`, `movedex.ron`, `itemdex.ron` (optional),
+//! the engine data described in [firecore_battle_tools::engine::loader] and `battle.ron`,
+//! a [BattleDefinition]. The first `External` player is controlled from the terminal.
+
+use std::{
+    io::{self, BufRead, Write},
+    thread,
+    time::Duration,
+};
+
+use rand::{rngs::SmallRng, SeedableRng};
+
+use firecore_battle_tools::{
+    battle::{
+        client::{BattleClient, ClientEvent},
+        data::{FieldEvent, Weather, WeatherEvent},
+        endpoint::MpscClient,
+        engine::BattleEngine,
+        host::definition::{BattleDefinition, LoadedBattle},
+        moves::ClientDamage,
+        pokedex::{ailment::Ailment, types::Effective},
+        pokemon::{volatile::VolatileEvent, Indexed, TeamIndex},
+        select::{BattleSelection, ClientActionType, PublicAction},
+        side::SideEvent,
+    },
+    engine::DefaultEngine,
+    load::{load_engine, read_ron, seed, Dexes, LoadError},
+};
+
+type Id = u8;
+
+/// How long to wait between updates when there is nothing to do.
+const POLL: Duration = Duration::from_millis(10);
+type Client = BattleClient<Id, (), MpscClient<Id, ()>>;
+
+fn main() {
+    let directory = match std::env::args().nth(1) {
+        Some(directory) => directory,
+        None => {
+            eprintln!("Usage: battle-cli <data directory>");
+            std::process::exit(1);
+        }
+    };
+
+    if let Err(err) = run(&directory) {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
+}
+
+fn run(directory: &str) -> Result<(), LoadError> {
+    let dexes = Dexes::load(directory)?;
+
+    let mut engine = DefaultEngine::<Id, ()>::new::<SmallRng>();
+    load_engine(directory, &dexes, &mut engine)?;
+
+    let mut random = SmallRng::seed_from_u64(seed());
+
+    let definition =
+        read_ron::<BattleDefinition<Id, ()>>(std::path::Path::new(directory).join("battle.ron"))?;
+
+    let LoadedBattle {
+        mut battle,
+        mut ais,
+        clients,
+    } = match definition.load::<DefaultEngine<Id, ()>>(
+        &mut random,
+        &dexes.pokedex,
+        &dexes.movedex,
+        &dexes.itemdex,
+    ) {
+        Ok(loaded) => loaded,
+        Err(err) => return Err(LoadError::Battle(err.to_string())),
+    };
+
+    let (player, client) = match clients.into_iter().next() {
+        Some(client) => client,
+        None => {
+            return Err(LoadError::Battle(
+                "the battle has no External player to control".to_owned(),
+            ))
+        }
+    };
+
+    let mut client = Client::new(client);
+
+    while battle.running() {
+        if let Err(errors) = battle.update(&mut random, &engine, &dexes.movedex) {
+            for err in errors {
+                println!("Host error: {:?}", err);
+            }
+        }
+
+        for ai in ais.iter_mut() {
+            if let Err(err) = ai.update(&mut random, &dexes.pokedex, &dexes.movedex, &dexes.itemdex)
+            {
+                println!("AI error: {:?}", err);
+            }
+        }
+
+        if let Err(err) = client.update(&dexes.pokedex, &dexes.movedex, &dexes.itemdex) {
+            println!("Client error: {:?}", err);
+            break;
+        }
+
+        let events = client.events().collect::<Vec<_>>();
+        for event in events {
+            render(&client, &dexes, &event);
+            let input = match event {
+                ClientEvent::Select(active, ..) => select(&client, &engine, &dexes, active),
+                ClientEvent::Faint(pokemon) if pokemon.team() == &player => {
+                    replace(&client, pokemon.index())
+                }
+                _ => Ok(()),
+            };
+            if input.is_err() {
+                println!("No more input, forfeiting.");
+                let _ = client.forfeit();
+                return Ok(());
+            }
+        }
+
+        thread::sleep(POLL);
+    }
+
+    Ok(())
+}
+
+fn name(client: &Client, dexes: &Dexes, pokemon: &TeamIndex<Id>) -> String {
+    match client.local() {
+        Some(local) if local.id == *pokemon.team() => local
+            .active(pokemon.index())
+            .map(|p| p.name().to_owned())
+            .unwrap_or_else(|| "Unknown".to_owned()),
+        _ => client
+            .remote(pokemon.team())
+            .and_then(|r| r.active(pokemon.index()))
+            .and_then(Option::as_ref)
+            .and_then(|p| p.clone().init(&dexes.pokedex))
+            .map(|p| format!("The foe's {}", p.name()))
+            .unwrap_or_else(|| "Unknown".to_owned()),
+    }
+}
+
+fn render(client: &Client, dexes: &Dexes, event: &ClientEvent<Id>) {
+    match event {
+        ClientEvent::Start => println!("The battle has started!"),
+        ClientEvent::AddOpponent(id) => {
+            if let Some(remote) = client.remote(id) {
+                println!("{} wants to battle!", remote.name());
+            }
+        }
+        ClientEvent::Announce(.., Some(user), kind) => {
+            let user = name(client, dexes, user);
+            match kind {
+                ClientActionType::Move(id) => println!(
+                    "{} used {}!",
+                    user,
+                    dexes
+                        .movedex
+                        .try_get(id)
+                        .map(|m| m.name.as_str())
+                        .unwrap_or("an unknown move")
+                ),
+                ClientActionType::Pokemon(..) => println!("{} is being switched out!", user),
+                ClientActionType::Item(Indexed(.., id)) => {
+                    println!("{} used a {}!", user, id.as_str())
+                }
+                ClientActionType::Recharge | ClientActionType::Post => (),
+            }
+        }
+        ClientEvent::Action(Indexed(target, action)) => {
+            let target_name = name(client, dexes, target);
+            match action {
+                PublicAction::SetHP(hp) => {
+                    if let ClientDamage::Result(result) = hp {
+                        if result.crit {
+                            println!("A critical hit!");
+                        }
+                        match result.effective {
+                            Effective::SuperEffective => println!("It's super effective!"),
+                            Effective::NotEffective => println!("It's not very effective..."),
+                            Effective::Ineffective => {
+                                println!("It doesn't affect {}...", target_name)
+                            }
+                            Effective::Effective => (),
+                        }
+                    }
+                    println!("{} is at {:.0}% HP", target_name, hp.damage() * 100.0);
+                }
+                PublicAction::AddStat(stat, stage) => println!(
+                    "{}'s {} {} by {}!",
+                    target_name,
+                    stat,
+                    if *stage < 0 { "fell" } else { "rose" },
+                    stage.abs()
+                ),
+                PublicAction::Ailment(Some(ailment)) => {
+                    println!("{} is now {:?}!", target_name, ailment.ailment)
+                }
+                PublicAction::Ailment(None) => println!("{} was cured!", target_name),
+                PublicAction::Switch(..) => println!("Go, {}!", target_name),
+                PublicAction::Reveal => (),
+                PublicAction::Cancel(reason) => {
+                    println!("{} could not move! ({})", target_name, reason)
+                }
+                PublicAction::Remove(reason) => {
+                    println!("{} left the battle! ({})", target_name, reason)
+                }
+                PublicAction::Weather(event) => weather(event),
+                PublicAction::Field(event) => field(event),
+                PublicAction::Side(event) => {
+                    let team = match client.is_local(target.team()) {
+                        true => "your team",
+                        false => "the foe's team",
+                    };
+                    match event {
+                        SideEvent::Start(condition) => {
+                            println!("{:?} now protects {}!", condition, team)
+                        }
+                        SideEvent::End(condition) => {
+                            println!("{}'s {:?} wore off.", team, condition)
+                        }
+                        SideEvent::Hazard(hazard) => {
+                            println!("{:?} were scattered around {}!", hazard, team)
+                        }
+                        SideEvent::Clear(hazard) => {
+                            println!("{:?} disappeared from around {}.", hazard, team)
+                        }
+                    }
+                }
+                PublicAction::Ability(ability) => {
+                    println!("[{}'s {}]", target_name, ability)
+                }
+                PublicAction::Item(item, consumed) => match consumed {
+                    true => println!("{} used up its {}!", target_name, item.as_str()),
+                    false => println!("[{}'s {}]", target_name, item.as_str()),
+                },
+                PublicAction::Hurt(ailment) => {
+                    println!("{} is hurt by its {:?}!", target_name, ailment)
+                }
+                PublicAction::Immobile(ailment) => match ailment {
+                    Ailment::Sleep => println!("{} is fast asleep.", target_name),
+                    Ailment::Freeze => println!("{} is frozen solid!", target_name),
+                    _ => println!("{} is paralyzed! It can't move!", target_name),
+                },
+                PublicAction::Recover(ailment) => match ailment {
+                    Ailment::Sleep => println!("{} woke up!", target_name),
+                    Ailment::Freeze => println!("{} thawed out!", target_name),
+                    _ => println!("{} recovered!", target_name),
+                },
+                PublicAction::Volatile(event) => match event {
+                    VolatileEvent::Start(status) => {
+                        println!("{} is now affected by {:?}!", target_name, status)
+                    }
+                    VolatileEvent::End(status) => {
+                        println!("{}'s {:?} ended.", target_name, status)
+                    }
+                    VolatileEvent::Hurt(status) => {
+                        println!("{} is hurt by {:?}!", target_name, status)
+                    }
+                    VolatileEvent::Immobile(status) => {
+                        println!("{} can't move because of {:?}!", target_name, status)
+                    }
+                    VolatileEvent::Blocked => {
+                        println!("The substitute took the hit for {}!", target_name)
+                    }
+                    VolatileEvent::Perish(turns) => {
+                        println!("{}'s perish count fell to {}.", target_name, turns)
+                    }
+                },
+                PublicAction::Hits(hits) => match hits {
+                    1 => println!("Hit 1 time!"),
+                    hits => println!("Hit {} times!", hits),
+                },
+                PublicAction::Charge => println!("{} is charging up!", target_name),
+                PublicAction::Recharge => println!("{} must recharge!", target_name),
+                PublicAction::Miss => println!("{}'s attack missed!", target_name),
+            }
+        }
+        ClientEvent::Weather(event) => weather(event),
+        ClientEvent::Field(event) => field(event),
+        ClientEvent::Faint(pokemon) => println!("{} fainted!", name(client, dexes, pokemon)),
+        ClientEvent::Replace(pokemon, ..) => {
+            println!("{} was sent out!", name(client, dexes, pokemon))
+        }
+        ClientEvent::Error(err) => println!("Error: {}", err),
+        ClientEvent::Remove(id, reason) => println!("Player #{} was removed ({:?})", id, reason),
+        ClientEvent::End(winner) => match winner {
+            Some(winner) if client.is_local(winner) => println!("You won!"),
+            Some(..) => println!("You lost!"),
+            None => println!("The battle ended in a draw."),
+        },
+        _ => (),
+    }
+}
+
+fn weather(event: &WeatherEvent) {
+    match event {
+        WeatherEvent::Start(weather) => match weather {
+            Weather::Rain => println!("It started to rain!"),
+            Weather::Sun => println!("The sunlight turned harsh!"),
+            Weather::Sandstorm => println!("A sandstorm kicked up!"),
+            Weather::Hail => println!("It started to hail!"),
+        },
+        WeatherEvent::Continue(weather) => match weather {
+            Weather::Rain => println!("Rain continues to fall."),
+            Weather::Sun => println!("The sunlight is strong."),
+            Weather::Sandstorm => println!("The sandstorm rages."),
+            Weather::Hail => println!("Hail continues to fall."),
+        },
+        WeatherEvent::End(weather) => match weather {
+            Weather::Rain => println!("The rain stopped."),
+            Weather::Sun => println!("The sunlight faded."),
+            Weather::Sandstorm => println!("The sandstorm subsided."),
+            Weather::Hail => println!("The hail stopped."),
+        },
+    }
+}
+
+fn field(event: &FieldEvent) {
+    match event {
+        FieldEvent::Start(id) => println!("{} is now in effect!", id),
+        FieldEvent::End(id) => println!("{} wore off.", id),
+    }
+}
+
+/// Read a line, failing once stdin is closed.
+fn prompt(message: &str) -> io::Result<String> {
+    print!("{} ", message);
+    let _ = io::stdout().flush();
+    let mut line = String::new();
+    match io::stdin().lock().read_line(&mut line)? {
+        0 => Err(io::ErrorKind::UnexpectedEof.into()),
+        _ => Ok(line.trim().to_owned()),
+    }
+}
+
+fn select(
+    client: &Client,
+    engine: &DefaultEngine<Id, ()>,
+    dexes: &Dexes,
+    active: usize,
+) -> io::Result<()> {
+    let (player, pokemon) = match client
+        .local()
+        .and_then(|local| local.active(active).map(|p| (local.id, p)))
+    {
+        Some(local) => local,
+        None => return Ok(()),
+    };
+
+    println!(
+        "What will {} do? ({}/{} HP)",
+        pokemon.name(),
+        pokemon.hp(),
+        pokemon.max_hp()
+    );
+    for (i, m) in pokemon.moves.iter().enumerate() {
+        println!(
+            "  {}: {} ({} PP)",
+            i,
+            dexes
+                .movedex
+                .try_get(m.id())
+                .map(|m| m.name.as_str())
+                .unwrap_or("Unknown"),
+            m.pp
+        );
+    }
+
+    loop {
+        let line = prompt("[m <move> | s <pokemon> | i <item> | f]:")?;
+        let mut args = line.split_whitespace();
+        let selection = match (args.next(), args.next()) {
+            (Some("m"), Some(index)) => match index
+                .parse::<usize>()
+                .ok()
+                .and_then(|i| pokemon.moves.get(i))
+            {
+                Some(m) => {
+                    let id = *m.id();
+                    let target = match engine
+                        .get_move(&id)
+                        .map(|m| m.target.needs_input())
+                        .unwrap_or_default()
+                    {
+                        true => target(client)?,
+                        false => None,
+                    };
+                    BattleSelection::Move(id, target)
+                }
+                None => continue,
+            },
+            (Some("s"), Some(index)) => match index.parse() {
+                Ok(index) => BattleSelection::Pokemon(index),
+                Err(..) => continue,
+            },
+            (Some("i"), Some(item)) => match item.parse() {
+                Ok(item) => BattleSelection::Item(Indexed(TeamIndex(player, active), item)),
+                Err(..) => continue,
+            },
+            (Some("f"), ..) => {
+                let _ = client.forfeit();
+                return Ok(());
+            }
+            _ => continue,
+        };
+        let _ = client.select(active, selection);
+        return Ok(());
+    }
+}
+
+fn target(client: &Client) -> io::Result<Option<TeamIndex<Id>>> {
+    let targets = client
+        .remotes()
+        .values()
+        .flat_map(|remote| {
+            remote
+                .active
+                .iter()
+                .enumerate()
+                .filter(|(.., a)| a.is_some())
+                .map(|(i, ..)| TeamIndex(remote.id, i))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    if targets.len() <= 1 {
+        return Ok(targets.into_iter().next());
+    }
+
+    for (i, target) in targets.iter().enumerate() {
+        println!("  {}: {}", i, target);
+    }
+
+    loop {
+        if let Some(target) = prompt("Target:")?
+            .parse::<usize>()
+            .ok()
+            .and_then(|i| targets.get(i))
+        {
+            return Ok(Some(target.clone()));
+        }
+    }
+}
+
+fn replace(client: &Client, active: usize) -> io::Result<()> {
+    let local = match client.local() {
+        Some(local) => local,
+        None => return Ok(()),
+    };
+
+    let remaining = local.remaining().collect::<Vec<_>>();
+    if remaining.is_empty() {
+        return Ok(());
+    }
+
+    for (i, pokemon) in remaining.iter() {
+        println!(
+            "  {}: {} Lv. {} ({}/{} HP)",
+            i,
+            pokemon.name(),
+            pokemon.level,
+            pokemon.hp(),
+            pokemon.max_hp()
+        );
+    }
+
+    loop {
+        if let Some(index) = prompt("Send out:")?
+            .parse::<usize>()
+            .ok()
+            .filter(|i| remaining.iter().any(|(r, ..)| r == i))
+        {
+            let _ = client.select_pokemon(active, index);
+            return Ok(());
+        }
+    }
+}
@@ -0,0 +1,48 @@
+//! Run many AI versus AI battles and print aggregate statistics.
+//!
+//! Usage: `battle-sim <config.ron> <data directory>`
+//!
+//! The data directory is laid out the same as for `battle-cli`,
+//! and the config is a [SimConfig](firecore_battle_tools::sim::SimConfig).
+
+use std::time::Instant;
+
+use rand::rngs::SmallRng;
+
+use firecore_battle_tools::{
+    load::{load_engine, read_ron, Dexes, LoadError},
+    sim::{simulate, SimConfig, SimEngine},
+};
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let (config, directory) = match (args.next(), args.next()) {
+        (Some(config), Some(directory)) => (config, directory),
+        _ => {
+            eprintln!("Usage: battle-sim <config.ron> <data directory>");
+            std::process::exit(1);
+        }
+    };
+
+    if let Err(err) = run(&config, &directory) {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
+}
+
+fn run(config: &str, directory: &str) -> Result<(), LoadError> {
+    let config = read_ron::<SimConfig>(config)?;
+    let dexes = Dexes::load(directory)?;
+
+    let mut engine = SimEngine::new::<SmallRng>();
+    load_engine(directory, &dexes, &mut engine)?;
+
+    let start = Instant::now();
+    let stats =
+        simulate(&config, &dexes, &engine).map_err(|err| LoadError::Battle(err.to_string()))?;
+
+    println!("{}", stats);
+    println!("Finished in {:.2}s", start.elapsed().as_secs_f32());
+
+    Ok(())
+}
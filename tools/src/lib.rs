@@ -0,0 +1,7 @@
+//! Tools for playtesting and simulating battles outside of a game
+
+pub extern crate firecore_battle as battle;
+pub extern crate firecore_battle_engine as engine;
+
+pub mod load;
+pub mod sim;
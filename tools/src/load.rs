@@ -0,0 +1,102 @@
+//! Reading game data from files
+
+use std::{
+    fmt::{Display, Formatter, Result as FmtResult},
+    fs,
+    hash::Hash,
+    path::{Path, PathBuf},
+};
+
+use serde::de::DeserializeOwned;
+
+use battle::pokedex::{item::Item, moves::Move, pokemon::Pokemon, Dex};
+use engine::{loader::DataError, DefaultEngine};
+
+/// The dexes a battle needs to initialize its pokemon.
+#[derive(Default)]
+pub struct Dexes {
+    pub pokedex: Dex<Pokemon>,
+    pub movedex: Dex<Move>,
+    pub itemdex: Dex<Item>,
+}
+
+#[derive(Debug)]
+pub enum LoadError {
+    Io(PathBuf, std::io::Error),
+    Ron(PathBuf, ron::error::SpannedError),
+    /// Every problem found in the engine's data.
+    Engine(Vec<DataError>),
+    /// The battle could not be created from the data.
+    Battle(String),
+}
+
+pub fn read_ron<T: DeserializeOwned>(path: impl AsRef<Path>) -> Result<T, LoadError> {
+    let path = path.as_ref();
+    let data = fs::read_to_string(path).map_err(|err| LoadError::Io(path.to_owned(), err))?;
+    ron::from_str(&data).map_err(|err| LoadError::Ron(path.to_owned(), err))
+}
+
+impl Dexes {
+    /// Load `pokedex.ron`, `movedex.ron` and, if present, `itemdex.ron` from a directory.
+    /// Each file holds a list of entries.
+    pub fn load(directory: impl AsRef<Path>) -> Result<Self, LoadError> {
+        let directory = directory.as_ref();
+        let mut dexes = Self::default();
+
+        for pokemon in read_ron::<Vec<Pokemon>>(directory.join("pokedex.ron"))? {
+            dexes.pokedex.insert(pokemon);
+        }
+
+        for m in read_ron::<Vec<Move>>(directory.join("movedex.ron"))? {
+            dexes.movedex.insert(m);
+        }
+
+        let items = directory.join("itemdex.ron");
+        if items.exists() {
+            for item in read_ron::<Vec<Item>>(items)? {
+                dexes.itemdex.insert(item);
+            }
+        }
+
+        Ok(dexes)
+    }
+}
+
+/// Load the moves, items and scripts of an engine from a directory,
+/// laid out as described in [engine::loader].
+pub fn load_engine<ID: Clone + Hash + Eq + Send + Sync + 'static, T: Send + Sync + 'static>(
+    directory: impl AsRef<Path>,
+    dexes: &Dexes,
+    engine: &mut DefaultEngine<ID, T>,
+) -> Result<(), LoadError> {
+    engine
+        .load(directory, &dexes.movedex, &dexes.itemdex)
+        .map_err(LoadError::Engine)
+}
+
+/// A random seed taken from the system time.
+pub fn seed() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(325218)
+}
+
+impl std::error::Error for LoadError {}
+
+impl Display for LoadError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::Io(path, err) => write!(f, "Could not read {}: {}", path.display(), err),
+            Self::Ron(path, err) => write!(f, "Could not parse {}: {}", path.display(), err),
+            Self::Engine(errors) => {
+                write!(f, "Could not load engine data:")?;
+                for err in errors {
+                    write!(f, "\n  {}", err)?;
+                }
+                Ok(())
+            }
+            Self::Battle(err) => write!(f, "Could not create battle: {}", err),
+        }
+    }
+}
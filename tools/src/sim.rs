@@ -0,0 +1,457 @@
+//! Headless AI versus AI battles
+
+use std::{
+    fmt::{Display, Formatter, Result as FmtResult},
+    sync::Arc,
+};
+
+use hashbrown::{HashMap, HashSet};
+use rand::{rngs::SmallRng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use battle::{
+    ai::BattleAi,
+    client::ClientEvent,
+    data::BattleData,
+    host::{definition::DefinitionError, Battle, PlayerData},
+    player::PlayerSettings,
+    pokedex::{
+        moves::MoveId,
+        pokemon::{
+            owned::{OwnedPokemon, SavedPokemon},
+            party::Party,
+            PokemonId,
+        },
+    },
+    pokemon::{Indexed, TeamIndex},
+    select::{ClientActionType, PublicAction},
+};
+use engine::DefaultEngine;
+
+use crate::load::Dexes;
+
+/// Players are identified by the index of their party in the config.
+pub type Id = usize;
+
+pub type SimEngine = DefaultEngine<Id, ()>;
+
+/// Describes a batch of battles to simulate.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SimConfig {
+    pub battles: usize,
+    /// Number of threads to run battles on
+    #[serde(default = "default_threads")]
+    pub threads: usize,
+    /// Seed of the first battle, every following battle adds one to it.
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// Battles that last longer than this many turns end in a draw.
+    #[serde(default = "default_max_turns")]
+    pub max_turns: usize,
+    #[serde(default)]
+    pub data: BattleData,
+    /// Every party fights in each battle.
+    pub parties: Vec<SimParty>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SimParty {
+    pub name: String,
+    pub pokemon: Vec<SavedPokemon>,
+}
+
+/// Outcomes tracked for a party, species or move.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Record {
+    /// Battles this took part in
+    pub battles: usize,
+    pub wins: usize,
+    /// Total damage dealt, in fractions of the targets' max HP.
+    pub damage: f32,
+}
+
+/// Aggregate statistics of many battles.
+#[derive(Debug, Default, Clone)]
+pub struct SimStats {
+    pub battles: usize,
+    pub draws: usize,
+    /// Battles stopped by an error of the host or an AI.
+    /// They do not count towards any record.
+    pub failed: usize,
+    pub errors: Vec<String>,
+    pub turns: usize,
+    pub parties: Vec<Record>,
+    pub species: HashMap<PokemonId, Record>,
+    pub moves: HashMap<MoveId, Record>,
+}
+
+const fn default_threads() -> usize {
+    1
+}
+
+const fn default_max_turns() -> usize {
+    500
+}
+
+impl Record {
+    pub fn win_rate(&self) -> f32 {
+        match self.battles {
+            0 => 0.0,
+            battles => self.wins as f32 / battles as f32,
+        }
+    }
+
+    fn merge(&mut self, other: &Self) {
+        self.battles += other.battles;
+        self.wins += other.wins;
+        self.damage += other.damage;
+    }
+}
+
+impl SimStats {
+    pub fn average_turns(&self) -> f32 {
+        match self.battles {
+            0 => 0.0,
+            battles => self.turns as f32 / battles as f32,
+        }
+    }
+
+    pub fn merge(&mut self, other: &Self) {
+        self.battles += other.battles;
+        self.draws += other.draws;
+        self.failed += other.failed;
+        self.errors.extend(other.errors.iter().cloned());
+        self.turns += other.turns;
+        if self.parties.len() < other.parties.len() {
+            self.parties.resize(other.parties.len(), Default::default());
+        }
+        for (record, other) in self.parties.iter_mut().zip(other.parties.iter()) {
+            record.merge(other);
+        }
+        for (id, other) in other.species.iter() {
+            self.species.entry(*id).or_default().merge(other);
+        }
+        for (id, other) in other.moves.iter() {
+            self.moves.entry(*id).or_default().merge(other);
+        }
+    }
+}
+
+/// Run every battle in the config, split across its threads.
+pub fn simulate(
+    config: &SimConfig,
+    dexes: &Dexes,
+    engine: &SimEngine,
+) -> Result<SimStats, DefinitionError<Id>> {
+    let seed = config.seed.unwrap_or_else(crate::load::seed);
+    let threads = config.threads.max(1);
+
+    std::thread::scope(|scope| {
+        let workers = (0..threads)
+            .map(|worker| {
+                scope.spawn(move || {
+                    let mut stats = SimStats::default();
+                    for battle in (worker..config.battles).step_by(threads) {
+                        stats.merge(&simulate_one(
+                            config,
+                            dexes,
+                            engine,
+                            seed.wrapping_add(battle as u64),
+                        )?);
+                    }
+                    Ok(stats)
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut stats = SimStats::default();
+        for worker in workers {
+            match worker.join() {
+                Ok(worker) => stats.merge(&worker?),
+                Err(panic) => std::panic::resume_unwind(panic),
+            }
+        }
+        Ok(stats)
+    })
+}
+
+/// Run a single battle with a seed.
+pub fn simulate_one(
+    config: &SimConfig,
+    dexes: &Dexes,
+    engine: &SimEngine,
+    seed: u64,
+) -> Result<SimStats, DefinitionError<Id>> {
+    let mut random = SmallRng::seed_from_u64(seed);
+
+    let parties = config
+        .parties
+        .iter()
+        .enumerate()
+        .map(|(id, party)| {
+            party
+                .pokemon
+                .iter()
+                .cloned()
+                .enumerate()
+                .map(|(index, p)| {
+                    p.init(&mut random, &dexes.pokedex, &dexes.movedex, &dexes.itemdex)
+                        .ok_or(DefinitionError::Pokemon(id, index))
+                })
+                .collect::<Result<Party<OwnedPokemon>, _>>()
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut ais = parties
+        .iter()
+        .map(|_| BattleAi::<Id, ()>::new())
+        .collect::<Vec<_>>();
+
+    let mut battle = Battle::<Id, (), SimEngine>::new(
+        config.data,
+        parties
+            .iter()
+            .zip(ais.iter())
+            .enumerate()
+            .map(|(id, (party, ai))| PlayerData {
+                id,
+                name: config.parties.get(id).map(|p| p.name.clone()),
+                party: party.clone(),
+                abilities: Default::default(),
+                bag: Default::default(),
+                trainer: Some(()),
+                settings: PlayerSettings { gains_exp: false },
+                endpoint: Arc::new(ai.endpoint().clone()),
+            }),
+    );
+
+    // each player only sees the battle until it leaves,
+    // so the player that stayed the longest has seen all of it
+    let mut observers = ais.iter().map(|_| Observer::default()).collect::<Vec<_>>();
+    let mut errors = Vec::new();
+
+    while battle.running()
+        && errors.is_empty()
+        && observers.iter().map(|o| o.turns).max().unwrap_or_default() < config.max_turns
+    {
+        if let Err(err) = battle.update(&mut random, engine, &dexes.movedex) {
+            errors.extend(
+                err.into_iter()
+                    .map(|err| format!("Battle with seed {}: host error {:?}", seed, err)),
+            );
+        }
+        for (id, (ai, observer)) in ais.iter_mut().zip(observers.iter_mut()).enumerate() {
+            if observer.left {
+                continue;
+            }
+            match ai.update(&mut random, &dexes.pokedex, &dexes.movedex, &dexes.itemdex) {
+                Ok(events) => observer.watch(ai, events),
+                Err(err) => errors.push(format!(
+                    "Battle with seed {}: AI of party #{} failed with {:?}",
+                    seed, id, err
+                )),
+            }
+        }
+    }
+
+    let observer = observers
+        .into_iter()
+        .max_by_key(|o| o.turns)
+        .unwrap_or_default();
+
+    if !errors.is_empty() {
+        return Ok(SimStats {
+            battles: 1,
+            failed: 1,
+            errors,
+            turns: observer.turns,
+            ..Default::default()
+        });
+    }
+
+    let winner = battle.winner().flatten().copied();
+
+    let mut stats = SimStats {
+        battles: 1,
+        draws: winner.is_none() as usize,
+        turns: observer.turns,
+        parties: vec![Default::default(); parties.len()],
+        ..Default::default()
+    };
+
+    for (id, party) in parties.iter().enumerate() {
+        let won = winner == Some(id);
+
+        if let Some(record) = stats.parties.get_mut(id) {
+            record.battles += 1;
+            record.wins += won as usize;
+            record.damage += observer.parties.get(&id).copied().unwrap_or_default();
+        }
+
+        // a species or move is in the battle once no matter how many times the party has it
+        let species = party
+            .iter()
+            .map(|pokemon| pokemon.pokemon.id)
+            .collect::<HashSet<_>>();
+        let moves = party
+            .iter()
+            .flat_map(|pokemon| pokemon.moves.iter().map(|m| *m.id()))
+            .collect::<HashSet<_>>();
+
+        for id in species {
+            let record = stats.species.entry(id).or_default();
+            record.battles += 1;
+            record.wins += won as usize;
+        }
+
+        for id in moves {
+            let record = stats.moves.entry(id).or_default();
+            record.battles += 1;
+            record.wins += won as usize;
+        }
+    }
+
+    for (id, damage) in observer.species {
+        stats.species.entry(id).or_default().damage += damage;
+    }
+
+    for (id, damage) in observer.moves {
+        stats.moves.entry(id).or_default().damage += damage;
+    }
+
+    Ok(stats)
+}
+
+/// Follows the results a player receives to measure damage dealt.
+#[derive(Default)]
+struct Observer {
+    turns: usize,
+    /// The player was removed or the battle ended
+    left: bool,
+    /// Last known HP of each active pokemon
+    hp: HashMap<TeamIndex<Id>, f32>,
+    user: Option<(TeamIndex<Id>, Option<MoveId>, Option<PokemonId>)>,
+    parties: HashMap<Id, f32>,
+    species: HashMap<PokemonId, f32>,
+    moves: HashMap<MoveId, f32>,
+}
+
+impl Observer {
+    fn species(ai: &BattleAi<Id, ()>, pokemon: &TeamIndex<Id>) -> Option<PokemonId> {
+        let client = ai.client();
+        match client.local() {
+            Some(local) if &local.id == pokemon.team() => {
+                local.active(pokemon.index()).map(|p| p.pokemon.id)
+            }
+            _ => client
+                .remote(pokemon.team())
+                .and_then(|r| r.active(pokemon.index()))
+                .and_then(Option::as_ref)
+                .map(|p| p.pokemon),
+        }
+    }
+
+    fn watch(&mut self, ai: &BattleAi<Id, ()>, events: Vec<ClientEvent<Id>>) {
+        for event in events {
+            match event {
+                ClientEvent::Announce(.., ClientActionType::Post) => {
+                    self.turns += 1;
+                    self.user = None;
+                }
+                ClientEvent::Announce(.., user, kind) => {
+                    self.user = user.map(|user| {
+                        let species = Self::species(ai, &user);
+                        let m = match kind {
+                            ClientActionType::Move(id) => Some(id),
+                            _ => None,
+                        };
+                        (user, m, species)
+                    });
+                }
+                ClientEvent::Action(Indexed(target, action)) => match action {
+                    PublicAction::SetHP(hp) => {
+                        let hp = hp.damage();
+                        let last = self.hp.insert(target.clone(), hp).unwrap_or(1.0);
+                        if let Some((user, m, species)) = self.user.as_ref() {
+                            let damage = last - hp;
+                            if user.team() != target.team() && damage > 0.0 {
+                                *self.parties.entry(*user.team()).or_default() += damage;
+                                if let Some(species) = species {
+                                    *self.species.entry(*species).or_default() += damage;
+                                }
+                                if let Some(m) = m {
+                                    *self.moves.entry(*m).or_default() += damage;
+                                }
+                            }
+                        }
+                    }
+                    PublicAction::Switch(..) => {
+                        if let Some((user, ..)) = self.user.as_ref() {
+                            self.hp.remove(user);
+                        }
+                    }
+                    _ => (),
+                },
+                ClientEvent::Replace(target, ..) => {
+                    self.hp.remove(&target);
+                }
+                ClientEvent::Remove(id, ..) => self.left |= ai.client().is_local(&id),
+                ClientEvent::End(..) => self.left = true,
+                _ => (),
+            }
+        }
+    }
+}
+
+impl Display for SimStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        writeln!(
+            f,
+            "{} battles, {} draws, {} failed, {:.1} turns on average",
+            self.battles,
+            self.draws,
+            self.failed,
+            self.average_turns()
+        )?;
+
+        if !self.errors.is_empty() {
+            writeln!(f, "Errors:")?;
+            for err in self.errors.iter() {
+                writeln!(f, "  {}", err)?;
+            }
+        }
+
+        writeln!(f, "Parties:")?;
+        for (id, record) in self.parties.iter().enumerate() {
+            writeln!(f, "  #{}: {}", id, record)?;
+        }
+
+        let mut species = self.species.iter().collect::<Vec<_>>();
+        species.sort_by(|a, b| b.1.win_rate().total_cmp(&a.1.win_rate()));
+        writeln!(f, "Species:")?;
+        for (id, record) in species {
+            writeln!(f, "  #{}: {}", id.0, record)?;
+        }
+
+        let mut moves = self.moves.iter().collect::<Vec<_>>();
+        moves.sort_by(|a, b| b.1.win_rate().total_cmp(&a.1.win_rate()));
+        writeln!(f, "Moves:")?;
+        for (id, record) in moves {
+            writeln!(f, "  {}: {}", id.0, record)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for Record {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(
+            f,
+            "{:.1}% wins of {} battles, {:.2} damage dealt",
+            self.win_rate() * 100.0,
+            self.battles,
+            self.damage
+        )
+    }
+}